@@ -2,38 +2,40 @@ use base64::Engine;
 use regex::Regex;
 use sha2::{Digest, Sha256};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Pinned in srcuri-wasm/Cargo.toml; the CLI must match it exactly
+const WASM_BINDGEN_VERSION: &str = "0.2.100";
 
 fn main() {
     println!("cargo:rerun-if-changed=src/templates/");
+    embed_build_info();
 
     let templates_dir = Path::new("src/templates");
     let out_dir = std::env::var("OUT_DIR").unwrap();
     let dest_path = Path::new(&out_dir).join("csp_hashes.rs");
 
     let mut hashes = Vec::new();
+    if let Some(glue) = build_srcuri_wasm(Path::new(&out_dir)) {
+        hashes.push(("SRCURI_WASM_JS_HASH".to_string(), sha256_base64(&glue)));
+    }
+    let script_re = Regex::new(r"(?s)<script>(.*?)</script>").unwrap();
 
     // Process each HTML template
     for entry in fs::read_dir(templates_dir).expect("Failed to read templates directory") {
         let entry = entry.expect("Failed to read directory entry");
         let path = entry.path();
 
-        if path.extension().map_or(false, |ext| ext == "html") {
+        if path.extension().is_some_and(|ext| ext == "html") {
             let content = fs::read_to_string(&path).expect("Failed to read template file");
             let filename = path.file_stem().unwrap().to_str().unwrap();
 
             // Extract all script blocks and compute hashes
-            let script_re = Regex::new(r"(?s)<script>(.*?)</script>").unwrap();
             for (i, cap) in script_re.captures_iter(&content).enumerate() {
                 let script_content = &cap[1];
-
-                // Compute SHA-256 hash
-                let mut hasher = Sha256::new();
-                hasher.update(script_content.as_bytes());
-                let hash = hasher.finalize();
-
-                // Base64 encode
-                let hash_b64 = base64::engine::general_purpose::STANDARD.encode(hash);
+                let hash_b64 = sha256_base64(script_content.as_bytes());
 
                 // Generate constant name: e.g., MIRROR_SCRIPT_0_HASH
                 let const_name = format!(
@@ -69,3 +71,171 @@ fn main() {
 
     fs::write(&dest_path, output).expect("Failed to write CSP hashes file");
 }
+
+/// Base64 SHA-256 digest, as CSP hash sources and SRI use it
+fn sha256_base64(content: &[u8]) -> String {
+    base64::engine::general_purpose::STANDARD.encode(Sha256::digest(content))
+}
+
+/// Compile srcuri-wasm for the browser and run wasm-bindgen on it, leaving
+/// `srcuri_wasm.js` and `srcuri_wasm_bg.wasm` in `OUT_DIR/wasm` and their
+/// URLs and integrity in `OUT_DIR/srcuri_wasm.rs`. Returns the glue script.
+///
+/// Opt-in, as it needs the wasm toolchain: with the `browser-wasm` feature the
+/// module is built here, and with `SORCERY_WASM_DIR` set the two files are
+/// taken from there instead, for builds where an earlier step produced them.
+/// Otherwise the assets are left empty and the pages parse through the server.
+fn build_srcuri_wasm(out_dir: &Path) -> Option<Vec<u8>> {
+    println!("cargo:rerun-if-changed=srcuri-wasm/src");
+    println!("cargo:rerun-if-changed=srcuri-wasm/Cargo.toml");
+    println!("cargo:rerun-if-env-changed=SORCERY_WASM_DIR");
+
+    let wasm_dir = out_dir.join("wasm");
+    fs::create_dir_all(&wasm_dir).expect("Failed to create wasm output directory");
+
+    match std::env::var_os("SORCERY_WASM_DIR") {
+        Some(prebuilt) => {
+            for name in ["srcuri_wasm.js", "srcuri_wasm_bg.wasm"] {
+                let source = Path::new(&prebuilt).join(name);
+                println!("cargo:rerun-if-changed={}", source.display());
+                fs::copy(&source, wasm_dir.join(name))
+                    .unwrap_or_else(|e| panic!("SORCERY_WASM_DIR: {}: {}", source.display(), e));
+            }
+        }
+        None if std::env::var_os("CARGO_FEATURE_BROWSER_WASM").is_some() => {
+            // A separate target dir, so this doesn't wait on the lock of the build running us
+            let target_dir = out_dir.join("wasm-target");
+            let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+            run(Command::new(cargo)
+                .args(["build", "--release", "-p", "srcuri-wasm", "--target", "wasm32-unknown-unknown"])
+                .arg("--target-dir")
+                .arg(&target_dir)
+                .env_remove("CARGO_ENCODED_RUSTFLAGS")
+                .env_remove("RUSTFLAGS"));
+
+            let module: PathBuf = target_dir.join("wasm32-unknown-unknown/release/srcuri_wasm.wasm");
+            run(Command::new("wasm-bindgen")
+                .args(["--target", "no-modules", "--no-typescript", "--out-name", "srcuri_wasm", "--out-dir"])
+                .arg(&wasm_dir)
+                .arg(&module));
+        }
+        None => {
+            for name in ["srcuri_wasm.js", "srcuri_wasm_bg.wasm"] {
+                fs::write(wasm_dir.join(name), b"").expect("Failed to write empty wasm asset");
+            }
+            let output = "// Auto-generated by build.rs - do not edit manually\n\n\
+                          pub const AVAILABLE: bool = false;\n\
+                          pub const GLUE_URL: &str = \"\";\n\
+                          pub const GLUE_INTEGRITY: &str = \"\";\n\
+                          pub const MODULE_URL: &str = \"\";\n";
+            fs::write(out_dir.join("srcuri_wasm.rs"), output).expect("Failed to write srcuri_wasm.rs");
+            return None;
+        }
+    }
+
+    let glue = fs::read(wasm_dir.join("srcuri_wasm.js")).expect("Failed to read srcuri_wasm.js");
+    let module = fs::read(wasm_dir.join("srcuri_wasm_bg.wasm")).expect("Failed to read srcuri_wasm_bg.wasm");
+
+    // Versioned URLs, so the assets can be cached as immutable
+    let version = |content: &[u8]| -> String {
+        Sha256::digest(content).iter().take(6).map(|b| format!("{:02x}", b)).collect()
+    };
+    let output = format!(
+        "// Auto-generated by build.rs - do not edit manually\n\n\
+         pub const AVAILABLE: bool = true;\n\
+         pub const GLUE_URL: &str = \"/static/srcuri_wasm.js?v={}\";\n\
+         pub const GLUE_INTEGRITY: &str = \"sha256-{}\";\n\
+         pub const MODULE_URL: &str = \"/static/srcuri_wasm_bg.wasm?v={}\";\n",
+        version(&glue),
+        sha256_base64(&glue),
+        version(&module),
+    );
+    fs::write(out_dir.join("srcuri_wasm.rs"), output).expect("Failed to write srcuri_wasm.rs");
+    Some(glue)
+}
+
+/// Run a wasm build step, failing the build with what to install when it's missing
+fn run(command: &mut Command) {
+    let program = command.get_program().to_string_lossy().into_owned();
+    let status = command.status().unwrap_or_else(|e| {
+        panic!(
+            "Failed to run {}: {}. Building the browser parser needs \
+             `rustup target add wasm32-unknown-unknown` and \
+             `cargo install wasm-bindgen-cli --version {}`, or SORCERY_WASM_DIR \
+             pointing at a prebuilt srcuri_wasm.js and srcuri_wasm_bg.wasm",
+            program, e, WASM_BINDGEN_VERSION
+        )
+    });
+    assert!(status.success(), "{} failed with {}", program, status);
+}
+
+/// Git SHA, build time and srcuri-core revision for `/version`, as
+/// `SORCERY_GIT_SHA`, `SORCERY_BUILD_TIME` and `SORCERY_SRCURI_CORE_REV`
+fn embed_build_info() {
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs/heads");
+    println!("cargo:rerun-if-changed=Cargo.lock");
+    println!("cargo:rerun-if-env-changed=SORCERY_GIT_SHA");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+
+    // Docker builds have no .git, so the SHA can be passed in
+    let git_sha = std::env::var("SORCERY_GIT_SHA")
+        .ok()
+        .filter(|sha| !sha.is_empty())
+        .or_else(|| {
+            let output = Command::new("git").args(["rev-parse", "--short=12", "HEAD"]).output().ok()?;
+            output.status.success().then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        })
+        .unwrap_or_else(|| "unknown".to_string());
+
+    // SOURCE_DATE_EPOCH keeps reproducible builds reproducible
+    let build_secs = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or_else(|| SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs());
+
+    let core_rev = fs::read_to_string("Cargo.lock")
+        .ok()
+        .and_then(|lock| srcuri_core_rev(&lock))
+        .unwrap_or_else(|| "unknown".to_string());
+
+    println!("cargo:rustc-env=SORCERY_GIT_SHA={}", git_sha);
+    println!("cargo:rustc-env=SORCERY_BUILD_TIME={}", rfc3339(build_secs));
+    println!("cargo:rustc-env=SORCERY_SRCURI_CORE_REV={}", core_rev);
+}
+
+/// The locked git commit of srcuri-core, or its version when it's a path override
+fn srcuri_core_rev(lock: &str) -> Option<String> {
+    let package = lock
+        .split("[[package]]")
+        .find(|package| package.contains("name = \"srcuri-core\""))?;
+    let field = |key: &str| {
+        package
+            .lines()
+            .find_map(|line| line.strip_prefix(key))
+            .map(|value| value.trim().trim_matches('"').to_string())
+    };
+    match field("source = ").and_then(|source| source.split_once('#').map(|(_, rev)| rev.to_string())) {
+        Some(rev) => Some(rev.chars().take(12).collect()),
+        None => field("version = ").map(|version| format!("{} (local)", version)),
+    }
+}
+
+/// UTC timestamp like 2024-05-01T12:00:00Z, without a date crate
+fn rfc3339(secs: u64) -> String {
+    let days = (secs / 86_400) as i64;
+    let (hour, minute, second) = (secs % 86_400 / 3600, secs % 3600 / 60, secs % 60);
+
+    // Civil-from-days, after Howard Hinnant's date algorithms
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", year, month, day, hour, minute, second)
+}
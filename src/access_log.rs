@@ -0,0 +1,69 @@
+// Access log
+// One event per request, after the response is produced. Fields come from the
+// redacted `LogContext`, so the access log never carries private paths unless
+// the tenant opted in. With `LOG_FORMAT=json` each event is one JSON line.
+
+use axum::{
+    body::Body,
+    extract::Request,
+    middleware::Next,
+    response::Response,
+};
+use std::time::Instant;
+use tracing::Span;
+
+use crate::client_ip::ClientIp;
+use crate::ratelimit::RateLimitOutcome;
+use crate::redact::LogContext;
+use crate::request_id::RequestId;
+use crate::subdomain::SubdomainMode;
+use crate::telemetry;
+use crate::tenant::ResolvedTenant;
+
+pub fn mode_name(mode: &SubdomainMode) -> &'static str {
+    match mode {
+        SubdomainMode::DirectProtocol => "direct_protocol",
+        SubdomainMode::WwwRedirect => "www_redirect",
+        SubdomainMode::EnterpriseTenant(_) => "enterprise_tenant",
+        SubdomainMode::Reserved(_) => "reserved",
+    }
+}
+
+/// Record the request's `LogContext` on the request span, then write the
+/// access log line
+pub async fn access_log_middleware(request: Request<Body>, next: Next) -> Response {
+    let start = Instant::now();
+    let tenant = request.extensions().get::<ResolvedTenant>().cloned().unwrap_or_else(ResolvedTenant::public);
+    let log_full_paths = tenant.config().is_some_and(|config| config.log_full_paths);
+
+    let context = LogContext::for_request(request.uri(), tenant.id().to_string(), log_full_paths);
+    let method = request.method().clone();
+    let request_id = request.extensions().get::<RequestId>().map(|RequestId(id)| id.clone());
+    let client_ip = request.extensions().get::<ClientIp>().map(|ClientIp(ip)| ip.to_string());
+    telemetry::record_context(&Span::current(), &method, &context);
+
+    let response = next.run(request).await;
+
+    let rate_limit = response
+        .extensions()
+        .get::<RateLimitOutcome>()
+        .map(|outcome| outcome.as_str())
+        .unwrap_or("exempt");
+    tracing::info!(
+        target: "sorcery_server::access",
+        request_id = request_id.as_deref().unwrap_or("-"),
+        method = %method,
+        mode = mode_name(&tenant.mode),
+        route = context.route,
+        tenant = %context.tenant,
+        provider = context.provider.as_deref().unwrap_or("-"),
+        remote_hash = context.remote_hash.as_deref().unwrap_or("-"),
+        path = context.full_path.as_deref().unwrap_or("-"),
+        status = response.status().as_u16(),
+        latency_ms = start.elapsed().as_secs_f64() * 1000.0,
+        rate_limit,
+        client_ip = client_ip.as_deref().unwrap_or("-"),
+        "request"
+    );
+    response
+}
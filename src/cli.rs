@@ -0,0 +1,156 @@
+use clap::{Parser, Subcommand};
+use std::path::{Path, PathBuf};
+
+use axum::http::Uri;
+use sorcery_server::config::ServerConfig;
+use sorcery_server::subdomain::SubdomainMode;
+use sorcery_server::tenant::{self, validate};
+
+#[derive(Parser)]
+#[command(name = "sorcery-server", version, about = "Web gateway for srcuri:// links")]
+pub struct Cli {
+    /// Server config file (TOML); defaults to SORCERY_CONFIG when set
+    #[arg(long, global = true)]
+    pub config: Option<PathBuf>,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Run the HTTP server (default)
+    Serve,
+    /// Inspect and manage tenant configs
+    #[command(subcommand)]
+    Tenants(TenantsCommand),
+}
+
+#[derive(Subcommand)]
+pub enum TenantsCommand {
+    /// Check every tenant file for syntax errors, schema errors and unknown keys
+    Validate {
+        /// Tenants directory (defaults to the configured tenants dir)
+        dir: Option<PathBuf>,
+    },
+    /// Create a starter tenant file
+    New {
+        /// Tenant subdomain, e.g. "acme" for acme.srcuri.com
+        name: String,
+        #[arg(long)]
+        dir: Option<PathBuf>,
+    },
+    /// Show which tenant config a Host header resolves to
+    Show {
+        /// Host header value, e.g. "acme.srcuri.com"
+        host: String,
+        #[arg(long)]
+        dir: Option<PathBuf>,
+        /// Base domains, comma-separated (defaults to the configured base domains)
+        #[arg(long, value_delimiter = ',')]
+        base_domain: Vec<String>,
+    },
+}
+
+/// Run a `tenants` subcommand and return the process exit code
+pub async fn run_tenants(command: TenantsCommand, config: &ServerConfig) -> i32 {
+    let default_dir = config.tenants.dir.clone();
+    match command {
+        TenantsCommand::Validate { dir } => {
+            validate_tenants(&dir.unwrap_or(default_dir), &config.tenants.options(&config.base_domains))
+        }
+        TenantsCommand::New { name, dir } => {
+            new_tenant(&dir.unwrap_or(default_dir), &name, &config.tenants.reserved_subdomains)
+        }
+        TenantsCommand::Show { host, dir, base_domain } => {
+            let base_domains = if base_domain.is_empty() { config.base_domains.clone() } else { base_domain };
+            show_tenant(dir.unwrap_or(default_dir), config.tenants.options(&base_domains), &host, &base_domains).await
+        }
+    }
+}
+
+fn validate_tenants(dir: &Path, options: &tenant::TenantOptions) -> i32 {
+    let reports = match validate::validate_dir(dir, options) {
+        Ok(reports) => reports,
+        Err(e) => {
+            eprintln!("{}: {}", dir.display(), e);
+            return 2;
+        }
+    };
+
+    let invalid = reports.iter().filter(|r| !r.is_valid()).count();
+    for report in &reports {
+        eprint!("{}", report);
+    }
+    println!("{} tenant file(s) checked, {} invalid", reports.len(), invalid);
+
+    if invalid == 0 { 0 } else { 1 }
+}
+
+fn new_tenant(dir: &Path, name: &str, reserved: &[String]) -> i32 {
+    // The name becomes a file name, so this also keeps it inside `dir`
+    if let Err(reason) = tenant::check_tenant_id(name, reserved) {
+        eprintln!("Invalid tenant name: {}", reason);
+        return 1;
+    }
+    let path = dir.join(format!("{}.json", name));
+    if path.exists() {
+        eprintln!("{} already exists", path.display());
+        return 1;
+    }
+
+    let config = validate::template_config(name);
+    let json = serde_json::to_string_pretty(&config).expect("TenantConfig serializes");
+    if let Err(e) = std::fs::create_dir_all(dir).and_then(|_| std::fs::write(&path, json + "\n")) {
+        eprintln!("Failed to write {}: {}", path.display(), e);
+        return 1;
+    }
+    println!("Created {}", path.display());
+    0
+}
+
+async fn show_tenant(dir: PathBuf, options: tenant::TenantOptions, host: &str, base_domains: &[String]) -> i32 {
+    let manager = tenant::TenantManager::with_options(dir.clone(), options);
+    manager.reload().await;
+    let subdomain = manager.tenant_id_for_host(host, base_domains);
+    let path = dir.join(format!("{}.json", subdomain));
+
+    println!("Host:      {}", host);
+    if let SubdomainMode::Reserved(name) = manager.detect_mode(host, &Uri::from_static("/"), base_domains) {
+        println!("Subdomain: {} (reserved, requests get a 404)", name);
+        return 0;
+    }
+    println!("Subdomain: {}", subdomain);
+    let config = match manager.lookup(&subdomain).await {
+        Some(config) => {
+            println!("File:      {}", path.display());
+            config
+        }
+        None => {
+            println!("File:      {} (missing or invalid, using default config)", path.display());
+            tenant::config::TenantConfig::default_config()
+        }
+    };
+    println!("{}", serde_json::to_string_pretty(&config).expect("TenantConfig serializes"));
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_tenant_rejects_invalid_names() {
+        let tmp = crate::test_support::temp_dir();
+        let dir = tmp.path();
+        let reserved = vec!["api".to_string()];
+
+        for name in ["../escape", "api", "Bad_Name", "a.b"] {
+            assert_eq!(new_tenant(&dir.join("tenants"), name, &reserved), 1, "{}", name);
+        }
+        assert!(!dir.join("escape.json").exists());
+        assert!(!dir.join("tenants").exists());
+
+        assert_eq!(new_tenant(&dir.join("tenants"), "acme", &reserved), 0);
+        assert!(dir.join("tenants/acme.json").exists());
+    }
+}
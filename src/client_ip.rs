@@ -0,0 +1,309 @@
+// Client IP resolution
+// Forwarding headers are only believed when the peer is a configured trusted
+// proxy, only the one header those proxies set is read, and the chain is
+// walked from the right so a client can't prepend a spoofed address.
+
+use axum::{
+    body::Body,
+    extract::{ConnectInfo, Request, State},
+    http::HeaderMap,
+    middleware::Next,
+    response::Response,
+};
+use ipnet::IpNet;
+use serde::Deserialize;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// The resolved client address, stored as a request extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientIp(pub IpAddr);
+
+/// Marks a request that arrived on a Unix socket. Such a peer has no address,
+/// and only processes allowed to open the socket file can connect, so it is
+/// trusted like a proxy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnixSocketPeer;
+
+/// The forwarding header the trusted proxies set. The other two are ignored,
+/// since a proxy that doesn't set them passes a client's own copies through.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ForwardedHeader {
+    /// RFC 7239 `Forwarded`, reading its `for=` parameters
+    Forwarded,
+    /// What nginx's `$proxy_add_x_forwarded_for` and most load balancers append
+    #[default]
+    XForwardedFor,
+    /// A single address, as set by nginx's `proxy_set_header X-Real-IP $remote_addr`
+    XRealIp,
+}
+
+impl FromStr for ForwardedHeader {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "forwarded" => Ok(ForwardedHeader::Forwarded),
+            "x-forwarded-for" => Ok(ForwardedHeader::XForwardedFor),
+            "x-real-ip" => Ok(ForwardedHeader::XRealIp),
+            other => Err(format!(
+                "expected \"forwarded\", \"x-forwarded-for\" or \"x-real-ip\", got \"{}\"",
+                other
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TrustedProxies {
+    networks: Vec<IpNet>,
+    header: ForwardedHeader,
+}
+
+impl TrustedProxies {
+    /// Parse CIDRs ("10.0.0.0/8", "fd00::/8") or bare addresses ("192.0.2.10")
+    pub fn parse(entries: &[String]) -> Result<Self, String> {
+        let networks = entries
+            .iter()
+            .map(|entry| {
+                let entry = entry.trim();
+                entry
+                    .parse::<IpNet>()
+                    .or_else(|_| entry.parse::<IpAddr>().map(IpNet::from))
+                    .map_err(|_| format!("{:?} is not an IP address or CIDR like \"10.0.0.0/8\"", entry))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { networks, header: ForwardedHeader::default() })
+    }
+
+    /// Read client addresses from `header` instead of `X-Forwarded-For`
+    pub fn with_header(mut self, header: ForwardedHeader) -> Self {
+        self.header = header;
+        self
+    }
+
+    pub fn is_trusted(&self, ip: IpAddr) -> bool {
+        self.networks.iter().any(|net| net.contains(&ip))
+    }
+
+    /// Resolve the client address for a request that arrived from `peer`.
+    ///
+    /// Headers are ignored unless `peer` is trusted. Otherwise the chain in
+    /// the configured forwarding header is walked right to left, skipping trusted proxies; the first untrusted hop is the
+    /// client. A malformed hop stops the walk at the last address we trust.
+    pub fn resolve(&self, peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+        let peer = peer.to_canonical();
+        if !self.is_trusted(peer) {
+            return peer;
+        }
+        self.walk_chain(peer, headers)
+    }
+
+    /// Resolve the client address for a request from a `UnixSocketPeer`. The
+    /// forwarding chain is always walked; without one the client is local.
+    pub fn resolve_unix(&self, headers: &HeaderMap) -> IpAddr {
+        self.walk_chain(IpAddr::V4(Ipv4Addr::LOCALHOST), headers)
+    }
+
+    fn walk_chain(&self, peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+        let mut client = peer;
+        for hop in forwarding_chain(headers, self.header).iter().rev() {
+            let Some(ip) = hop else {
+                break;
+            };
+            client = ip.to_canonical();
+            if !self.is_trusted(client) {
+                break;
+            }
+        }
+        client
+    }
+}
+
+/// Addresses from `header`, left to right. `None` marks an entry that isn't
+/// an IP (e.g. "unknown" or "_hidden").
+fn forwarding_chain(headers: &HeaderMap, header: ForwardedHeader) -> Vec<Option<IpAddr>> {
+    match header {
+        ForwardedHeader::Forwarded => header_values(headers, "forwarded")
+            .flat_map(|value| value.split(',').map(str::to_string).collect::<Vec<_>>())
+            .map(|element| {
+                element
+                    .split(';')
+                    .find_map(|pair| {
+                        let (key, value) = pair.trim().split_once('=')?;
+                        key.eq_ignore_ascii_case("for").then(|| value.trim().trim_matches('"').to_string())
+                    })
+                    .and_then(|node| parse_node(&node))
+            })
+            .collect(),
+        ForwardedHeader::XForwardedFor => header_values(headers, "x-forwarded-for")
+            .flat_map(|value| value.split(',').map(|hop| parse_node(hop.trim())).collect::<Vec<_>>())
+            .collect(),
+        ForwardedHeader::XRealIp => header_values(headers, "x-real-ip").map(|value| parse_node(value.trim())).collect(),
+    }
+}
+
+fn header_values<'a>(headers: &'a HeaderMap, name: &str) -> impl Iterator<Item = &'a str> {
+    headers.get_all(name).into_iter().filter_map(|value| value.to_str().ok())
+}
+
+/// Parse "192.0.2.1", "192.0.2.1:4711", "2001:db8::1" or "[2001:db8::1]:4711"
+fn parse_node(node: &str) -> Option<IpAddr> {
+    if let Ok(ip) = node.parse::<IpAddr>() {
+        return Some(ip);
+    }
+    if let Ok(addr) = node.parse::<SocketAddr>() {
+        return Some(addr.ip());
+    }
+    node.strip_prefix('[')
+        .and_then(|rest| rest.split(']').next())
+        .and_then(|ip| ip.parse().ok())
+}
+
+/// Resolve the client IP and store it as a `ClientIp` request extension
+pub async fn client_ip_middleware(
+    State(proxies): State<Arc<TrustedProxies>>,
+    mut request: Request<Body>,
+    next: Next,
+) -> Response {
+    let client = if request.extensions().get::<UnixSocketPeer>().is_some() {
+        proxies.resolve_unix(request.headers())
+    } else {
+        // Without ConnectInfo (tests, or a server built without it) there's no
+        // peer to vouch for any header
+        let peer = request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip())
+            .unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+        proxies.resolve(peer, request.headers())
+    };
+    request.extensions_mut().insert(ClientIp(client));
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn proxies(entries: &[&str]) -> TrustedProxies {
+        TrustedProxies::parse(&entries.iter().map(|s| s.to_string()).collect::<Vec<_>>()).unwrap()
+    }
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut map = HeaderMap::new();
+        for (name, value) in pairs {
+            map.append(*name, value.parse().unwrap());
+        }
+        map
+    }
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_parse_rejects_garbage() {
+        assert!(TrustedProxies::parse(&["10.0.0.0/8".to_string(), "::1".to_string()]).is_ok());
+        assert!(TrustedProxies::parse(&["10.0.0.0/33".to_string()]).is_err());
+        assert!(TrustedProxies::parse(&["proxy.internal".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_untrusted_peer_headers_are_ignored() {
+        let proxies = proxies(&["10.0.0.0/8"]);
+        let headers = headers(&[("x-forwarded-for", "198.51.100.1"), ("x-real-ip", "198.51.100.2")]);
+        assert_eq!(proxies.resolve(ip("203.0.113.7"), &headers), ip("203.0.113.7"));
+    }
+
+    #[test]
+    fn test_no_trusted_proxies_means_peer_address() {
+        let headers = headers(&[("x-forwarded-for", "198.51.100.1")]);
+        assert_eq!(TrustedProxies::default().resolve(ip("10.0.0.5"), &headers), ip("10.0.0.5"));
+    }
+
+    #[test]
+    fn test_unix_socket_peer_is_trusted_but_loopback_is_not() {
+        let headers = headers(&[("x-forwarded-for", "198.51.100.1")]);
+        let proxies = TrustedProxies::default();
+        assert_eq!(proxies.resolve_unix(&headers), ip("198.51.100.1"));
+        assert_eq!(proxies.resolve(ip("127.0.0.1"), &headers), ip("127.0.0.1"));
+        assert_eq!(proxies.resolve_unix(&HeaderMap::new()), ip("127.0.0.1"));
+    }
+
+    #[test]
+    fn test_walks_chain_from_the_right() {
+        let proxies = proxies(&["10.0.0.0/8"]);
+        // The client prepended a spoofed address; the trusted proxy appended the real one
+        let headers = headers(&[("x-forwarded-for", "1.2.3.4, 203.0.113.7, 10.0.0.3")]);
+        assert_eq!(proxies.resolve(ip("10.0.0.2"), &headers), ip("203.0.113.7"));
+    }
+
+    #[test]
+    fn test_multiple_header_lines_are_one_chain() {
+        let proxies = proxies(&["10.0.0.0/8"]);
+        let headers = headers(&[("x-forwarded-for", "1.2.3.4"), ("x-forwarded-for", "203.0.113.7")]);
+        assert_eq!(proxies.resolve(ip("10.0.0.2"), &headers), ip("203.0.113.7"));
+    }
+
+    #[test]
+    fn test_all_trusted_uses_leftmost() {
+        let proxies = proxies(&["10.0.0.0/8"]);
+        let headers = headers(&[("x-forwarded-for", "10.1.1.1, 10.2.2.2")]);
+        assert_eq!(proxies.resolve(ip("10.0.0.2"), &headers), ip("10.1.1.1"));
+    }
+
+    #[test]
+    fn test_malformed_hop_stops_walk() {
+        let proxies = proxies(&["10.0.0.0/8"]);
+        let headers = headers(&[("x-forwarded-for", "203.0.113.7, garbage, 10.0.0.3")]);
+        assert_eq!(proxies.resolve(ip("10.0.0.2"), &headers), ip("10.0.0.3"));
+    }
+
+    #[test]
+    fn test_forwarded_header_when_configured() {
+        let proxies = proxies(&["10.0.0.0/8"]).with_header(ForwardedHeader::Forwarded);
+        let headers = headers(&[
+            ("forwarded", "for=198.51.100.9;proto=https, for=\"[2001:db8::1]:4711\""),
+            ("x-forwarded-for", "192.0.2.1"),
+        ]);
+        assert_eq!(proxies.resolve(ip("10.0.0.2"), &headers), ip("2001:db8::1"));
+    }
+
+    #[test]
+    fn test_client_forwarded_header_is_ignored_behind_x_forwarded_for_proxy() {
+        // The proxy appends to X-Forwarded-For and passes the client's own
+        // Forwarded header through untouched
+        let proxies = proxies(&["10.0.0.0/8"]);
+        let spoofed = headers(&[("forwarded", "for=1.2.3.4"), ("x-forwarded-for", "203.0.113.7")]);
+        assert_eq!(proxies.resolve(ip("10.0.0.2"), &spoofed), ip("203.0.113.7"));
+
+        // Without the configured header there is no chain, so the proxy is the client
+        let no_chain = headers(&[("forwarded", "for=1.2.3.4"), ("x-real-ip", "1.2.3.5")]);
+        assert_eq!(proxies.resolve(ip("10.0.0.2"), &no_chain), ip("10.0.0.2"));
+    }
+
+    #[test]
+    fn test_x_real_ip_when_configured_and_ports() {
+        let proxies = proxies(&["10.0.0.0/8"]).with_header(ForwardedHeader::XRealIp);
+        let headers = headers(&[("x-real-ip", "203.0.113.7:5555"), ("x-forwarded-for", "1.2.3.4")]);
+        assert_eq!(proxies.resolve(ip("10.0.0.2"), &headers), ip("203.0.113.7"));
+    }
+
+    #[test]
+    fn test_forwarded_header_names() {
+        assert_eq!("X-Forwarded-For".parse(), Ok(ForwardedHeader::XForwardedFor));
+        assert_eq!("forwarded".parse(), Ok(ForwardedHeader::Forwarded));
+        assert_eq!("x-real-ip".parse(), Ok(ForwardedHeader::XRealIp));
+        assert!("x-client-ip".parse::<ForwardedHeader>().is_err());
+    }
+
+    #[test]
+    fn test_ipv4_mapped_peer_is_canonicalized() {
+        let proxies = proxies(&["10.0.0.0/8"]);
+        let headers = headers(&[("x-forwarded-for", "203.0.113.7")]);
+        assert_eq!(proxies.resolve(ip("::ffff:10.0.0.2"), &headers), ip("203.0.113.7"));
+    }
+}
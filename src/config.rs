@@ -0,0 +1,837 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::client_ip::{ForwardedHeader, TrustedProxies};
+use crate::subdomain;
+use crate::tenant::TenantOptions;
+
+/// Server configuration. Loaded from an optional TOML file, then overridden
+/// by environment variables, then validated as a whole.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    /// Addresses to listen on, e.g. "0.0.0.0:3000" or "[::]:3000"
+    pub listen: Vec<SocketAddr>,
+    /// Unix domain socket to also serve plain HTTP on, for a reverse proxy on the same host
+    pub unix_socket: Option<PathBuf>,
+    /// Octal permissions for `unix_socket`, e.g. "660" for the owner and group
+    pub unix_socket_mode: String,
+    /// Apex domains the server answers on; the first is canonical
+    pub base_domains: Vec<String>,
+    /// Proxies (CIDRs or addresses) whose forwarding headers are believed
+    pub trusted_proxies: Vec<String>,
+    /// The one header `trusted_proxies` set the client address in; the other
+    /// forwarding headers are ignored
+    pub forwarded_header: ForwardedHeader,
+    pub rate_limit: RateLimitConfig,
+    pub cors: CorsConfig,
+    pub cache: CacheConfig,
+    pub tenants: TenantsConfig,
+    pub metrics: MetricsConfig,
+    pub logging: LoggingConfig,
+    pub telemetry: TelemetryConfig,
+    pub shutdown: ShutdownConfig,
+    pub tls: TlsConfig,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoggingConfig {
+    /// `text` for human-readable lines, `json` for one JSON object per line
+    pub format: LogFormat,
+    /// Key for the remote hashes in request logs. Set it to get hashes that
+    /// stay stable across restarts and instances; unset uses a random key.
+    pub redaction_key: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            other => Err(format!("expected \"text\" or \"json\", got \"{}\"", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TlsConfig {
+    /// HTTPS listeners; empty disables TLS
+    pub listen: Vec<SocketAddr>,
+    /// PEM certificate chain served when no custom-domain certificate matches
+    pub cert: Option<PathBuf>,
+    /// PEM private key for `cert`
+    pub key: Option<PathBuf>,
+    /// Directory of `<hostname>.crt` and `<hostname>.key` pairs for tenant
+    /// custom domains, chosen by SNI
+    pub certs_dir: Option<PathBuf>,
+    /// Seconds between checks for rotated certificate files; 0 disables polling (SIGHUP still reloads)
+    pub reload_interval: u64,
+    /// Plain-HTTP listeners that redirect every request to HTTPS
+    pub redirect_listen: Vec<SocketAddr>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ShutdownConfig {
+    /// Seconds readiness fails before listeners stop accepting, so load
+    /// balancers notice and stop routing here
+    pub readiness_delay: u64,
+    /// Seconds in-flight requests get to finish before connections are closed
+    pub drain_timeout: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TelemetryConfig {
+    /// OTLP/HTTP collector base URL, e.g. "http://localhost:4318"; spans are
+    /// posted to `/v1/traces` under it. Unset disables export.
+    pub otlp_endpoint: Option<String>,
+    /// `service.name` resource attribute on exported spans
+    pub service_name: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MetricsConfig {
+    /// Serve Prometheus metrics at /metrics. Off by default, since without
+    /// `listen` they're served to anyone on the public listeners.
+    pub enabled: bool,
+    /// Serve /metrics only on this separate admin address instead of the
+    /// public listeners
+    pub listen: Option<SocketAddr>,
+}
+
+/// Rate-limit policy for each route group. Limits are tracked per client IP
+/// and tenant, so traffic to one tenant doesn't use up another's budget.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RateLimitConfig {
+    /// `/health`, `/health/live` and `/health/ready`; off by default so load-balancer probes are never limited
+    pub health: RateLimitPolicy,
+    /// `/static/*` and the favicons
+    #[serde(rename = "static")]
+    pub static_assets: RateLimitPolicy,
+    /// Mirror, provider and passthrough pages
+    pub pages: RateLimitPolicy,
+    /// `/api/*`
+    pub api: RateLimitPolicy,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RateLimitPolicy {
+    /// Defaults to true, so writing a policy table turns limiting on
+    pub enabled: bool,
+    /// Seconds between replenished requests
+    pub per_second: u64,
+    /// Requests a client may make in a burst before being limited
+    pub burst_size: u32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CorsConfig {
+    /// Origins allowed to call the server cross-origin; "*" allows any
+    pub allowed_origins: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CacheConfig {
+    /// `max-age` in seconds for /static/app.js
+    pub app_js_max_age: u64,
+    /// `max-age` in seconds for the favicons
+    pub favicon_max_age: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TenantsConfig {
+    /// Directory holding one `<tenant>.json` file per tenant
+    pub dir: PathBuf,
+    /// Seconds between checks of `dir` for changed files; 0 disables polling
+    pub poll_interval: u64,
+    pub negative_cache_size: usize,
+    /// Seconds an unknown subdomain is remembered before re-checking disk
+    pub negative_ttl: u64,
+    pub strict: bool,
+    pub reserved_subdomains: Vec<String>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            listen: vec![SocketAddr::from(([0, 0, 0, 0], 3000))],
+            unix_socket: None,
+            unix_socket_mode: "660".to_string(),
+            base_domains: vec!["srcuri.com".to_string()],
+            trusted_proxies: Vec::new(),
+            forwarded_header: ForwardedHeader::default(),
+            rate_limit: RateLimitConfig::default(),
+            cors: CorsConfig::default(),
+            cache: CacheConfig::default(),
+            tenants: TenantsConfig::default(),
+            metrics: MetricsConfig::default(),
+            logging: LoggingConfig::default(),
+            telemetry: TelemetryConfig::default(),
+            shutdown: ShutdownConfig::default(),
+            tls: TlsConfig::default(),
+        }
+    }
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            health: RateLimitPolicy { enabled: false, ..RateLimitPolicy::default() },
+            static_assets: RateLimitPolicy { burst_size: 120, ..RateLimitPolicy::default() },
+            pages: RateLimitPolicy::default(),
+            api: RateLimitPolicy::default(),
+        }
+    }
+}
+
+impl Default for RateLimitPolicy {
+    fn default() -> Self {
+        // 60 requests per minute (1 request per second on average)
+        Self { enabled: true, per_second: 1, burst_size: 60 }
+    }
+}
+
+impl RateLimitConfig {
+    pub fn policies(&self) -> [(&'static str, &RateLimitPolicy); 4] {
+        [
+            ("health", &self.health),
+            ("static", &self.static_assets),
+            ("pages", &self.pages),
+            ("api", &self.api),
+        ]
+    }
+}
+
+impl Default for TlsConfig {
+    fn default() -> Self {
+        Self {
+            listen: Vec::new(),
+            cert: None,
+            key: None,
+            certs_dir: None,
+            reload_interval: 60,
+            redirect_listen: Vec::new(),
+        }
+    }
+}
+
+impl Default for ShutdownConfig {
+    fn default() -> Self {
+        // Together within the 30s Docker gives a container before SIGKILL
+        Self { readiness_delay: 5, drain_timeout: 20 }
+    }
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self { otlp_endpoint: None, service_name: "sorcery-server".to_string() }
+    }
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        Self { allowed_origins: vec!["*".to_string()] }
+    }
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self { app_js_max_age: 86400, favicon_max_age: 7776000 }
+    }
+}
+
+impl Default for TenantsConfig {
+    fn default() -> Self {
+        let options = TenantOptions::default();
+        Self {
+            dir: PathBuf::from("sorcery-server/tenants"),
+            poll_interval: 2,
+            negative_cache_size: options.negative_cache_size,
+            negative_ttl: options.negative_ttl.as_secs(),
+            strict: options.strict,
+            reserved_subdomains: options.reserved_subdomains,
+        }
+    }
+}
+
+impl TenantsConfig {
+    /// Options for a `TenantManager` on a server answering on `base_domains`
+    pub fn options(&self, base_domains: &[String]) -> TenantOptions {
+        TenantOptions {
+            negative_cache_size: self.negative_cache_size,
+            negative_ttl: Duration::from_secs(self.negative_ttl),
+            strict: self.strict,
+            reserved_subdomains: self.reserved_subdomains.clone(),
+            base_domains: base_domains.to_vec(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io { path: PathBuf, source: std::io::Error },
+    Parse { path: PathBuf, message: String },
+    Env { var: &'static str, value: String, message: String },
+    Invalid(Vec<String>),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            ConfigError::Parse { path, message } => write!(f, "{}: {}", path.display(), message.trim_end()),
+            ConfigError::Env { var, value, message } => write!(f, "{}={:?}: {}", var, value, message),
+            ConfigError::Invalid(problems) => {
+                write!(f, "invalid configuration:")?;
+                for problem in problems {
+                    write!(f, "\n  - {}", problem)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl ServerConfig {
+    /// Load `path` (or the defaults when `None`), apply environment overrides
+    /// and validate the result
+    pub fn load(path: Option<&Path>) -> Result<Self, ConfigError> {
+        let mut config = match path {
+            Some(path) => Self::from_file(path)?,
+            None => Self::default(),
+        };
+        config.apply_env(|var| std::env::var(var).ok())?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|source| ConfigError::Io { path: path.to_path_buf(), source })?;
+        toml::from_str(&content).map_err(|e| ConfigError::Parse { path: path.to_path_buf(), message: e.to_string() })
+    }
+
+    /// `unix_socket_mode` as permission bits, `None` unless it's octal up to 777
+    pub fn socket_mode(&self) -> Option<u32> {
+        u32::from_str_radix(&self.unix_socket_mode, 8).ok().filter(|mode| *mode <= 0o777)
+    }
+
+    /// Override file values with environment variables. `env` looks a
+    /// variable up, so tests don't have to touch the process environment.
+    pub fn apply_env(&mut self, env: impl Fn(&str) -> Option<String>) -> Result<(), ConfigError> {
+        if let Some(value) = env("LISTEN") {
+            self.listen = parse_list(&value)
+                .iter()
+                .map(|addr| parse_env("LISTEN", addr))
+                .collect::<Result<_, _>>()?;
+        }
+        // PORT predates LISTEN and keeps working, but only when there is one
+        // listener; with several it can't say which one it means
+        if let Some(value) = env("PORT") {
+            let port: u16 = parse_env("PORT", &value)?;
+            match self.listen.as_mut_slice() {
+                [addr] => addr.set_port(port),
+                listen => {
+                    return Err(ConfigError::Env {
+                        var: "PORT",
+                        value,
+                        message: format!("needs exactly one listen address, found {}; set LISTEN instead", listen.len()),
+                    })
+                }
+            }
+        }
+        if let Some(value) = env("UNIX_SOCKET") {
+            self.unix_socket = Some(PathBuf::from(value));
+        }
+        if let Some(value) = env("UNIX_SOCKET_MODE") {
+            self.unix_socket_mode = value;
+        }
+        if let Some(value) = env("BASE_DOMAIN") {
+            self.base_domains = parse_list(&value);
+        }
+        if let Some(value) = env("TRUSTED_PROXIES") {
+            self.trusted_proxies = parse_list(&value);
+        }
+        if let Some(value) = env("FORWARDED_HEADER") {
+            self.forwarded_header = parse_env("FORWARDED_HEADER", &value)?;
+        }
+        if let Some(value) = env("CORS_ORIGINS") {
+            self.cors.allowed_origins = parse_list(&value);
+        }
+        // The env shorthands tune the page and API policies, which carry user traffic
+        if let Some(value) = env("RATE_LIMIT_PER_SECOND") {
+            let per_second = parse_env("RATE_LIMIT_PER_SECOND", &value)?;
+            self.rate_limit.pages.per_second = per_second;
+            self.rate_limit.api.per_second = per_second;
+        }
+        if let Some(value) = env("RATE_LIMIT_BURST") {
+            let burst_size = parse_env("RATE_LIMIT_BURST", &value)?;
+            self.rate_limit.pages.burst_size = burst_size;
+            self.rate_limit.api.burst_size = burst_size;
+        }
+        if let Some(value) = env("METRICS_ENABLED") {
+            self.metrics.enabled = value == "1" || value == "true";
+        }
+        if let Some(value) = env("METRICS_LISTEN") {
+            self.metrics.listen = Some(parse_env("METRICS_LISTEN", &value)?);
+        }
+        if let Some(value) = env("LOG_FORMAT") {
+            self.logging.format = parse_env("LOG_FORMAT", &value)?;
+        }
+        if let Some(value) = env("LOG_REDACTION_KEY") {
+            self.logging.redaction_key = Some(value);
+        }
+        if let Some(value) = env("OTEL_EXPORTER_OTLP_ENDPOINT") {
+            self.telemetry.otlp_endpoint = Some(value);
+        }
+        if let Some(value) = env("OTEL_SERVICE_NAME") {
+            self.telemetry.service_name = value;
+        }
+        if let Some(value) = env("TLS_LISTEN") {
+            self.tls.listen = parse_list(&value)
+                .iter()
+                .map(|addr| parse_env("TLS_LISTEN", addr))
+                .collect::<Result<_, _>>()?;
+        }
+        if let Some(value) = env("TLS_CERT") {
+            self.tls.cert = Some(PathBuf::from(value));
+        }
+        if let Some(value) = env("TLS_KEY") {
+            self.tls.key = Some(PathBuf::from(value));
+        }
+        if let Some(value) = env("TLS_CERTS_DIR") {
+            self.tls.certs_dir = Some(PathBuf::from(value));
+        }
+        if let Some(value) = env("TLS_REDIRECT_LISTEN") {
+            self.tls.redirect_listen = parse_list(&value)
+                .iter()
+                .map(|addr| parse_env("TLS_REDIRECT_LISTEN", addr))
+                .collect::<Result<_, _>>()?;
+        }
+        if let Some(value) = env("SHUTDOWN_READINESS_DELAY") {
+            self.shutdown.readiness_delay = parse_env("SHUTDOWN_READINESS_DELAY", &value)?;
+        }
+        if let Some(value) = env("SHUTDOWN_DRAIN_TIMEOUT") {
+            self.shutdown.drain_timeout = parse_env("SHUTDOWN_DRAIN_TIMEOUT", &value)?;
+        }
+        if let Some(value) = env("TENANTS_DIR") {
+            self.tenants.dir = PathBuf::from(value);
+        }
+        if let Some(value) = env("TENANTS_POLL_INTERVAL") {
+            self.tenants.poll_interval = parse_env("TENANTS_POLL_INTERVAL", &value)?;
+        }
+        if let Some(value) = env("TENANT_NEGATIVE_CACHE_SIZE") {
+            self.tenants.negative_cache_size = parse_env("TENANT_NEGATIVE_CACHE_SIZE", &value)?;
+        }
+        if let Some(value) = env("TENANT_NEGATIVE_TTL") {
+            self.tenants.negative_ttl = parse_env("TENANT_NEGATIVE_TTL", &value)?;
+        }
+        if let Some(value) = env("STRICT_TENANTS") {
+            self.tenants.strict = value == "1" || value == "true";
+        }
+        if let Some(value) = env("RESERVED_SUBDOMAINS") {
+            self.tenants.reserved_subdomains = parse_list(&value).into_iter().map(|s| s.to_lowercase()).collect();
+        }
+        Ok(())
+    }
+
+    /// Check the whole config, reporting every problem at once
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut problems = Vec::new();
+
+        if self.listen.is_empty() && self.tls.listen.is_empty() && self.unix_socket.is_none() {
+            problems.push("listen: at least one address is required (or tls.listen or unix_socket)".to_string());
+        }
+        if self.socket_mode().is_none() {
+            problems.push(format!("unix_socket_mode: {:?} is not octal permissions like \"660\"", self.unix_socket_mode));
+        }
+        let all_listeners: Vec<(&str, &SocketAddr)> = self
+            .listen
+            .iter()
+            .map(|addr| ("listen", addr))
+            .chain(self.tls.listen.iter().map(|addr| ("tls.listen", addr)))
+            .chain(self.tls.redirect_listen.iter().map(|addr| ("tls.redirect_listen", addr)))
+            .collect();
+        for (i, (key, addr)) in all_listeners.iter().enumerate() {
+            if all_listeners[..i].iter().any(|(_, earlier)| earlier == addr) {
+                problems.push(format!("{}: {} is listed more than once", key, addr));
+            }
+        }
+
+        if !self.tls.listen.is_empty() && (self.tls.cert.is_none() || self.tls.key.is_none()) {
+            problems.push("tls: cert and key are required when tls.listen is set".to_string());
+        }
+        if self.tls.listen.is_empty() && !self.tls.redirect_listen.is_empty() {
+            problems.push("tls.redirect_listen: needs tls.listen to redirect to".to_string());
+        }
+
+        if let Some(admin) = self.metrics.listen {
+            if all_listeners.iter().any(|(_, addr)| **addr == admin) {
+                problems.push(format!("metrics.listen: {} is also a public listener", admin));
+            }
+        }
+
+        if self.logging.redaction_key.as_ref().is_some_and(|key| key.len() < 16) {
+            problems.push("logging.redaction_key must be at least 16 characters".to_string());
+        }
+
+        if let Some(endpoint) = &self.telemetry.otlp_endpoint {
+            let valid = url::Url::parse(endpoint)
+                .is_ok_and(|url| matches!(url.scheme(), "http" | "https") && url.host().is_some());
+            if !valid {
+                problems.push(format!(
+                    "telemetry.otlp_endpoint: {:?} is not a URL like \"http://localhost:4318\"",
+                    endpoint
+                ));
+            }
+        }
+        if self.telemetry.service_name.is_empty() {
+            problems.push("telemetry.service_name must not be empty".to_string());
+        }
+
+        if self.shutdown.drain_timeout == 0 {
+            problems.push("shutdown.drain_timeout must be at least 1".to_string());
+        }
+
+        if self.base_domains.is_empty() {
+            problems.push("base_domains: at least one domain is required".to_string());
+        }
+        for domain in &self.base_domains {
+            let valid = domain.split('.').all(subdomain::is_valid_dns_label);
+            if !valid {
+                problems.push(format!("base_domains: {:?} is not a lowercase hostname like \"srcuri.com\"", domain));
+            }
+        }
+
+        if let Err(problem) = TrustedProxies::parse(&self.trusted_proxies) {
+            problems.push(format!("trusted_proxies: {}", problem));
+        }
+
+        for (group, policy) in self.rate_limit.policies() {
+            if let Err(problem) = policy.validate() {
+                problems.push(format!("rate_limit.{}.{}", group, problem));
+            }
+        }
+
+        if self.cors.allowed_origins.len() > 1 && self.cors.allowed_origins.iter().any(|o| o == "*") {
+            problems.push("cors.allowed_origins: \"*\" can't be combined with other origins".to_string());
+        }
+        for origin in self.cors.allowed_origins.iter().filter(|o| *o != "*") {
+            let valid = url::Url::parse(origin).is_ok_and(|url| {
+                matches!(url.scheme(), "http" | "https")
+                    && url.host().is_some()
+                    && url.path() == "/"
+                    && !origin.ends_with('/')
+            });
+            if !valid {
+                problems.push(format!(
+                    "cors.allowed_origins: {:?} is not an origin like \"https://example.com\"",
+                    origin
+                ));
+            }
+        }
+
+        if self.tenants.negative_cache_size == 0 {
+            problems.push("tenants.negative_cache_size must be at least 1".to_string());
+        }
+        for name in &self.tenants.reserved_subdomains {
+            if !subdomain::is_valid_dns_label(name) {
+                problems.push(format!("tenants.reserved_subdomains: {:?} is not a valid DNS label", name));
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(problems))
+        }
+    }
+}
+
+impl RateLimitPolicy {
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.enabled {
+            return Ok(());
+        }
+        if self.per_second == 0 {
+            return Err("per_second must be at least 1".to_string());
+        }
+        if self.burst_size == 0 {
+            return Err("burst_size must be at least 1".to_string());
+        }
+        Ok(())
+    }
+}
+
+fn parse_list(value: &str) -> Vec<String> {
+    value.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect()
+}
+
+fn parse_env<T: FromStr>(var: &'static str, value: &str) -> Result<T, ConfigError>
+where
+    T::Err: fmt::Display,
+{
+    value.trim().parse().map_err(|e: T::Err| ConfigError::Env {
+        var,
+        value: value.to_string(),
+        message: e.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn env(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        move |var| vars.get(var).cloned()
+    }
+
+    fn parse(toml: &str) -> ServerConfig {
+        toml::from_str(toml).unwrap()
+    }
+
+    #[test]
+    fn test_defaults_are_valid() {
+        let config = ServerConfig::default();
+        config.validate().unwrap();
+        assert_eq!(config.listen, vec!["0.0.0.0:3000".parse().unwrap()]);
+        assert_eq!(config.base_domains, vec!["srcuri.com"]);
+        assert!(!config.metrics.enabled);
+    }
+
+    #[test]
+    fn test_example_file_matches_defaults() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("config/sorcery.example.toml");
+        let config = ServerConfig::from_file(&path).unwrap();
+        config.validate().unwrap();
+        assert!(!config.metrics.enabled, "the example must not expose /metrics");
+        assert_eq!(config.listen, ServerConfig::default().listen);
+        assert_eq!(config.forwarded_header, ServerConfig::default().forwarded_header);
+    }
+
+    #[test]
+    fn test_parses_full_file() {
+        let config = parse(
+            r#"
+            listen = ["0.0.0.0:8080", "[::]:8080"]
+            base_domains = ["srcuri.com", "srcuri.co.uk"]
+            trusted_proxies = ["10.0.0.0/8", "fd00::/8", "192.0.2.10"]
+
+            [rate_limit.pages]
+            per_second = 2
+            burst_size = 30
+
+            [rate_limit.health]
+            burst_size = 5
+
+            [cors]
+            allowed_origins = ["https://app.example.com"]
+
+            [cache]
+            app_js_max_age = 60
+
+            [tenants]
+            dir = "/etc/sorcery/tenants"
+            strict = true
+            "#,
+        );
+        config.validate().unwrap();
+        assert_eq!(config.listen.len(), 2);
+        assert!(config.listen[1].is_ipv6());
+        assert_eq!(config.rate_limit.pages.burst_size, 30);
+        assert_eq!(config.rate_limit.api.burst_size, 60);
+        assert!(config.rate_limit.health.enabled);
+        assert!(!ServerConfig::default().rate_limit.health.enabled);
+        assert_eq!(config.cache.app_js_max_age, 60);
+        assert_eq!(config.cache.favicon_max_age, 7776000);
+        assert_eq!(config.tenants.dir, PathBuf::from("/etc/sorcery/tenants"));
+        assert!(config.tenants.strict);
+        assert_eq!(config.tenants.poll_interval, 2);
+    }
+
+    #[test]
+    fn test_unknown_key_is_rejected() {
+        let err = toml::from_str::<ServerConfig>("[rate_limit.api]\nburst = 5\n").unwrap_err();
+        assert!(err.to_string().contains("burst"), "{}", err);
+    }
+
+    #[test]
+    fn test_bad_address_is_rejected() {
+        assert!(toml::from_str::<ServerConfig>("listen = [\"localhost\"]\n").is_err());
+    }
+
+    #[test]
+    fn test_env_overrides_file() {
+        let mut config = parse("listen = [\"0.0.0.0:8080\"]\nbase_domains = [\"srcuri.com\"]\n");
+        config
+            .apply_env(env(&[
+                ("PORT", "9000"),
+                ("BASE_DOMAIN", "srcuri.com, links.example.com"),
+                ("STRICT_TENANTS", "true"),
+                ("TENANTS_DIR", "/tmp/tenants"),
+            ]))
+            .unwrap();
+        assert_eq!(config.listen, vec!["0.0.0.0:9000".parse::<SocketAddr>().unwrap()]);
+        assert_eq!(config.base_domains, vec!["srcuri.com", "links.example.com"]);
+        assert!(config.tenants.strict);
+        assert_eq!(config.tenants.dir, PathBuf::from("/tmp/tenants"));
+    }
+
+    #[test]
+    fn test_listen_env_replaces_listeners() {
+        let mut config = ServerConfig::default();
+        config.apply_env(env(&[("LISTEN", "127.0.0.1:4000,[::1]:4000")])).unwrap();
+        assert_eq!(config.listen.len(), 2);
+        assert!(config.listen[1].is_ipv6());
+    }
+
+    #[test]
+    fn test_port_env_needs_a_single_listener() {
+        let mut config = parse("listen = [\"0.0.0.0:8080\", \"[::]:8080\"]\n");
+        let err = config.apply_env(env(&[("PORT", "9000")])).unwrap_err();
+        assert!(err.to_string().contains("found 2; set LISTEN instead"), "{}", err);
+
+        // LISTEN is applied first, so the two together work
+        let mut config = parse("listen = [\"0.0.0.0:8080\", \"[::]:8080\"]\n");
+        config.apply_env(env(&[("LISTEN", "[::]:8080"), ("PORT", "9000")])).unwrap();
+        assert_eq!(config.listen, vec!["[::]:9000".parse::<SocketAddr>().unwrap()]);
+    }
+
+    #[test]
+    fn test_log_format() {
+        assert_eq!(parse("[logging]\nformat = \"json\"\n").logging.format, LogFormat::Json);
+        assert_eq!(ServerConfig::default().logging.format, LogFormat::Text);
+        let mut config = ServerConfig::default();
+        config.apply_env(env(&[("LOG_FORMAT", "json")])).unwrap();
+        assert_eq!(config.logging.format, LogFormat::Json);
+        assert!(config.apply_env(env(&[("LOG_FORMAT", "yaml")])).is_err());
+    }
+
+    #[test]
+    fn test_forwarded_header() {
+        assert_eq!(ServerConfig::default().forwarded_header, ForwardedHeader::XForwardedFor);
+        assert_eq!(parse("forwarded_header = \"x-real-ip\"\n").forwarded_header, ForwardedHeader::XRealIp);
+        assert!(toml::from_str::<ServerConfig>("forwarded_header = \"client-ip\"\n").is_err());
+        let mut config = ServerConfig::default();
+        config.apply_env(env(&[("FORWARDED_HEADER", "forwarded")])).unwrap();
+        assert_eq!(config.forwarded_header, ForwardedHeader::Forwarded);
+    }
+
+    #[test]
+    fn test_bad_env_value_names_the_variable() {
+        let mut config = ServerConfig::default();
+        let err = config.apply_env(env(&[("PORT", "eighty")])).unwrap_err();
+        assert!(err.to_string().starts_with("PORT=\"eighty\""), "{}", err);
+    }
+
+    #[test]
+    fn test_validation_reports_every_problem() {
+        let config = parse(
+            r#"
+            listen = []
+            base_domains = ["Srcuri.com"]
+            trusted_proxies = ["10.0.0.0/40"]
+            [rate_limit.api]
+            per_second = 0
+            [cors]
+            allowed_origins = ["*", "example.com"]
+            "#,
+        );
+        let ConfigError::Invalid(problems) = config.validate().unwrap_err() else {
+            panic!("expected validation errors");
+        };
+        assert_eq!(problems.len(), 6, "{:?}", problems);
+    }
+
+    #[test]
+    fn test_telemetry_endpoint() {
+        let mut config = ServerConfig::default();
+        assert_eq!(config.telemetry.otlp_endpoint, None);
+        config
+            .apply_env(env(&[("OTEL_EXPORTER_OTLP_ENDPOINT", "http://otel-collector:4318"), ("OTEL_SERVICE_NAME", "links")]))
+            .unwrap();
+        config.validate().unwrap();
+        assert_eq!(config.telemetry.service_name, "links");
+
+        config.telemetry.otlp_endpoint = Some("otel-collector:4318".to_string());
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_tls_listener_config() {
+        let config = parse(
+            r#"
+            listen = []
+            [tls]
+            listen = ["0.0.0.0:443"]
+            redirect_listen = ["0.0.0.0:80"]
+            cert = "/etc/sorcery/tls/default.crt"
+            key = "/etc/sorcery/tls/default.key"
+            certs_dir = "/etc/sorcery/tls/domains"
+            "#,
+        );
+        config.validate().unwrap();
+        assert_eq!(config.tls.reload_interval, 60);
+
+        let mut missing_key = config.clone();
+        missing_key.tls.key = None;
+        assert!(missing_key.validate().is_err());
+
+        let mut clash = config.clone();
+        clash.tls.redirect_listen = clash.tls.listen.clone();
+        assert!(clash.validate().is_err());
+
+        let mut redirect_only = ServerConfig::default();
+        redirect_only.apply_env(env(&[("TLS_REDIRECT_LISTEN", "0.0.0.0:80")])).unwrap();
+        assert!(redirect_only.validate().is_err());
+    }
+
+    #[test]
+    fn test_unix_socket_config() {
+        let config = parse("listen = []\nunix_socket = \"/run/sorcery/http.sock\"\n");
+        config.validate().unwrap();
+        assert_eq!(config.socket_mode(), Some(0o660));
+
+        let mut config = ServerConfig::default();
+        config.apply_env(env(&[("UNIX_SOCKET", "/run/sorcery/http.sock"), ("UNIX_SOCKET_MODE", "0666")])).unwrap();
+        assert_eq!(config.socket_mode(), Some(0o666));
+
+        for bad in ["rw-rw----", "1777", "8"] {
+            config.unix_socket_mode = bad.to_string();
+            assert!(config.validate().is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_duplicate_listener_is_rejected() {
+        let config = parse("listen = [\"0.0.0.0:3000\", \"0.0.0.0:3000\"]\n");
+        assert!(config.validate().is_err());
+    }
+}
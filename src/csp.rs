@@ -15,7 +15,8 @@ pub async fn csp_middleware(request: Request<Body>, next: Next) -> Response<Body
     let mut response = next.run(request).await;
 
     // Build the CSP header value
-    // - script-src: Only allow scripts with matching hashes (computed at build time)
+    // - script-src: Only allow scripts with matching hashes (computed at build time),
+    //   and compiling the srcuri-wasm parser module
     // - style-src: Allow inline styles (lower risk than scripts)
     // - object-src: Block all plugins (Flash, Java, etc.)
     // - base-uri: Prevent base tag injection
@@ -23,7 +24,7 @@ pub async fn csp_middleware(request: Request<Body>, next: Next) -> Response<Body
     // - form-action: Only allow forms to submit to same origin
     let csp_value = format!(
         "default-src 'self'; \
-         script-src {}; \
+         script-src {} 'wasm-unsafe-eval'; \
          style-src 'self' 'unsafe-inline'; \
          object-src 'none'; \
          base-uri 'self'; \
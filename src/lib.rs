@@ -1,13 +1,53 @@
+pub mod access_log;
+pub mod client_ip;
+pub mod config;
 pub mod csp;
+#[cfg(unix)]
+pub mod listener;
+pub mod metrics;
 pub mod parsing;
+pub mod ratelimit;
+pub mod redact;
+pub mod request_id;
 pub mod routes;
+pub mod shutdown;
 pub mod subdomain;
+pub mod telemetry;
 pub mod tenant;
+#[cfg(test)]
+mod test_support;
+pub mod tls;
 
+use axum::Router;
 use std::sync::Arc;
+use tower_http::trace::TraceLayer;
 
 #[derive(Clone)]
 pub struct AppState {
     pub tenant_manager: Arc<tenant::TenantManager>,
-    pub base_domain: String,
+    /// Apex domains the server answers on; the first is canonical
+    pub base_domains: Vec<String>,
+    /// Flips readiness to failing when the server starts shutting down
+    pub shutdown: shutdown::Shutdown,
+}
+
+impl AppState {
+    /// Canonical domain, used for absolute links and when a request has no Host
+    pub fn primary_domain(&self) -> &str {
+        self.base_domains.first().map(String::as_str).unwrap_or("srcuri.com")
+    }
+}
+
+/// Wrap `router` in the per-request layers every listener shares, from the
+/// outside in: client address, request ID, the trace span, tenant resolution,
+/// the access log and metrics. The span is opened before the tenant is
+/// resolved so subdomain detection and tenant lookup are children of it.
+pub fn with_request_layers(router: Router, state: AppState, trusted_proxies: Arc<client_ip::TrustedProxies>) -> Router {
+    router
+        .layer(axum::middleware::from_fn(metrics::metrics_middleware))
+        .layer(axum::middleware::from_fn(access_log::access_log_middleware))
+        .layer(axum::middleware::from_fn_with_state(state, tenant::resolve_tenant_middleware))
+        .layer(TraceLayer::new_for_http().make_span_with(telemetry::request_span))
+        .layer(axum::middleware::from_fn(request_id::request_id_middleware))
+        .layer(axum::middleware::from_fn_with_state(trusted_proxies, client_ip::client_ip_middleware))
 }
@@ -0,0 +1,123 @@
+// Listeners beyond a TCP bind
+// A Unix domain socket for a reverse proxy on the same host, and sockets
+// inherited through systemd socket activation (`LISTEN_FDS`). Unix-socket
+// peers have no IP address; their requests carry a `UnixSocketPeer` marker
+// instead, and the client IP comes from the proxy's forwarding headers.
+
+use axum::{body::Body, http::Request, Router};
+use hyper::body::Incoming;
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::server::conn::auto;
+use hyper_util::server::graceful::GracefulShutdown;
+use listenfd::ListenFd;
+use std::future::Future;
+use std::io;
+use std::fs::DirBuilder;
+use std::os::unix::fs::{DirBuilderExt, FileTypeExt, PermissionsExt};
+use std::path::Path;
+use tokio::net::{TcpListener, UnixListener};
+use tower::ServiceExt;
+
+use crate::client_ip::UnixSocketPeer;
+
+/// A listening socket passed in by the service manager
+pub enum Inherited {
+    Tcp(TcpListener),
+    Unix(UnixListener),
+}
+
+/// Listening sockets from systemd socket activation, in `LISTEN_FDS` order.
+/// Empty when the process wasn't socket-activated.
+pub fn inherited() -> io::Result<Vec<Inherited>> {
+    let mut fds = ListenFd::from_env();
+    let mut listeners = Vec::new();
+    for idx in 0..fds.len() {
+        // A socket of the wrong kind is left in place, so try TCP first, then Unix
+        if let Ok(Some(listener)) = fds.take_tcp_listener(idx) {
+            listener.set_nonblocking(true)?;
+            listeners.push(Inherited::Tcp(TcpListener::from_std(listener)?));
+        } else if let Some(listener) = fds.take_unix_listener(idx)? {
+            listener.set_nonblocking(true)?;
+            listeners.push(Inherited::Unix(UnixListener::from_std(listener)?));
+        }
+    }
+    Ok(listeners)
+}
+
+/// Bind a Unix socket at `path` with permissions `mode`. A socket file left
+/// by a previous run is replaced, but not one another server is still
+/// accepting on, and never a file that isn't a socket.
+pub fn bind_unix(path: &Path, mode: u32) -> io::Result<UnixListener> {
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => {
+            if std::os::unix::net::UnixStream::connect(path).is_ok() {
+                return Err(io::Error::new(io::ErrorKind::AddrInUse, "another server is listening on it"));
+            }
+            std::fs::remove_file(path)?;
+        }
+        Ok(_) => return Err(io::Error::new(io::ErrorKind::AlreadyExists, "exists and is not a socket")),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+
+    // The socket is created with the umask applied. Bind it inside a 0700
+    // directory next to `path`, where nothing else can reach it, set `mode`
+    // and only then move it into place.
+    let parent = path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let file_name = path.file_name().ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no file name"))?;
+    let private_dir = parent.join(format!(".{}.{}", file_name.to_string_lossy(), uuid::Uuid::new_v4().simple()));
+    DirBuilder::new().mode(0o700).create(&private_dir)?;
+
+    let staged = private_dir.join(file_name);
+    let bound = UnixListener::bind(&staged).and_then(|listener| {
+        std::fs::set_permissions(&staged, std::fs::Permissions::from_mode(mode))?;
+        std::fs::rename(&staged, path)?;
+        Ok(listener)
+    });
+    let _ = std::fs::remove_file(&staged);
+    std::fs::remove_dir(&private_dir)?;
+    bound
+}
+
+/// Serve `app` on a Unix socket until `stopped` resolves, then wait for
+/// in-flight connections to finish
+pub async fn serve_unix(
+    listener: UnixListener,
+    app: Router,
+    stopped: impl Future<Output = ()> + Send + 'static,
+) -> io::Result<()> {
+    let graceful = GracefulShutdown::new();
+    let builder = auto::Builder::new(TokioExecutor::new());
+    tokio::pin!(stopped);
+
+    loop {
+        let stream = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    // Usually EMFILE; back off instead of spinning
+                    tracing::warn!("Failed to accept on Unix socket: {}", e);
+                    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+                    continue;
+                }
+            },
+            _ = &mut stopped => break,
+        };
+
+        let app = app.clone();
+        let service = hyper::service::service_fn(move |mut request: Request<Incoming>| {
+            request.extensions_mut().insert(UnixSocketPeer);
+            app.clone().oneshot(request.map(Body::new))
+        });
+        let connection = graceful.watch(builder.serve_connection_with_upgrades(TokioIo::new(stream), service).into_owned());
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                tracing::debug!("Unix socket connection error: {}", e);
+            }
+        });
+    }
+
+    drop(listener);
+    graceful.shutdown().await;
+    Ok(())
+}
@@ -3,7 +3,7 @@ use axum::{
     Router,
     response::{Response, Redirect, IntoResponse},
     body::Body,
-    http::{StatusCode, header, Uri},
+    http::{HeaderMap, HeaderValue, StatusCode, header, Uri},
     extract::{Host, Query},
 };
 use std::net::SocketAddr;
@@ -11,107 +11,385 @@ use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::{Duration, SystemTime};
 use httpdate::HttpDate;
-use tower_http::cors::{CorsLayer, Any};
-use tower_http::trace::TraceLayer;
-use tower_governor::{GovernorLayer, governor::GovernorConfigBuilder, key_extractor::SmartIpKeyExtractor};
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, Layer};
 
-use sorcery_server::{AppState, csp, routes, tenant, subdomain::{self, SubdomainMode}};
+use clap::Parser;
+use sorcery_server::{AppState, client_ip, csp, metrics, ratelimit::{self, RateLimiter}, redact, routes, shutdown::{self, Shutdown}, telemetry, tenant::{self, ResolvedTenant}, tls, config::{LogFormat, ServerConfig}, subdomain::{self, SubdomainMode}};
+
+mod cli;
+// The binary's tests only need some of the helpers
+#[cfg(test)]
+#[path = "test_support.rs"]
+#[allow(dead_code)]
+mod test_support;
 
 #[tokio::main]
 async fn main() {
+    let cli = cli::Cli::parse();
+
+    let config_path = cli.config.clone().or_else(|| std::env::var_os("SORCERY_CONFIG").map(PathBuf::from));
+    let config = match ServerConfig::load(config_path.as_deref()) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Configuration error: {}", e);
+            std::process::exit(2);
+        }
+    };
+
+    if let Some(cli::Command::Tenants(command)) = cli.command {
+        std::process::exit(cli::run_tenants(command, &config).await);
+    }
+
+    let tracer_provider = config.telemetry.otlp_endpoint.as_deref().map(|endpoint| {
+        telemetry::tracer_provider(endpoint, &config.telemetry.service_name).unwrap_or_else(|e| {
+            eprintln!("Configuration error: telemetry.otlp_endpoint: {}", e);
+            std::process::exit(2);
+        })
+    });
+
+    // RUST_LOG filters only the logs; the OpenTelemetry layer brings its own filter
+    let log_filter = tracing_subscriber::EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| "sorcery_server=debug,tower_http=debug".into());
+    let log_layer = match config.logging.format {
+        LogFormat::Text => tracing_subscriber::fmt::layer().boxed(),
+        LogFormat::Json => tracing_subscriber::fmt::layer().json().flatten_event(true).boxed(),
+    };
     tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "sorcery_server=debug,tower_http=debug".into()),
-        )
-        .with(tracing_subscriber::fmt::layer())
+        .with(log_layer.with_filter(log_filter))
+        .with(tracer_provider.as_ref().map(telemetry::layer))
         .init();
 
-    let tenants_dir = std::env::var("TENANTS_DIR")
-        .map(PathBuf::from)
-        .unwrap_or_else(|_| PathBuf::from("sorcery-server/tenants"));
+    redact::init(config.logging.redaction_key.as_deref());
 
-    let base_domain = std::env::var("BASE_DOMAIN")
-        .unwrap_or_else(|_| "srcuri.com".to_string());
+    let tenant_manager = Arc::new(tenant::TenantManager::with_options(
+        config.tenants.dir.clone(),
+        config.tenants.options(&config.base_domains),
+    ));
+    tenant_manager.reload().await;
 
-    let tenant_manager = Arc::new(tenant::TenantManager::new(tenants_dir));
+    // Poll the tenants directory for changes (0 disables polling; SIGHUP still reloads)
+    if config.tenants.poll_interval > 0 {
+        tenant_manager.clone().watch(Duration::from_secs(config.tenants.poll_interval));
+    }
 
-    let state = AppState { tenant_manager, base_domain };
+    let cert_store = (!config.tls.listen.is_empty()).then(|| {
+        let store = tls::CertStore::load(&config.tls).unwrap_or_else(|e| {
+            eprintln!("Configuration error: tls: {}", e);
+            std::process::exit(2);
+        });
+        Arc::new(store)
+    });
+    if let Some(store) = cert_store.as_ref().filter(|_| config.tls.reload_interval > 0) {
+        store.clone().watch(Duration::from_secs(config.tls.reload_interval));
+    }
+
+    #[cfg(unix)]
+    spawn_sighup_reload(tenant_manager.clone(), cert_store.clone());
+
+    let rate_limiter = Arc::new(RateLimiter::new(config.rate_limit.clone()));
+    rate_limiter.clone().spawn_cleanup(Duration::from_secs(60));
 
-    // Rate limiting: 60 requests per minute per IP (1 request per second on average)
-    let governor_config = Arc::new(
-        GovernorConfigBuilder::default()
-            .per_second(1)
-            .burst_size(60)
-            .key_extractor(SmartIpKeyExtractor)
-            .finish()
-            .expect("Failed to build rate limiter config")
+    let trusted_proxies = Arc::new(
+        client_ip::TrustedProxies::parse(&config.trusted_proxies)
+            .expect("trusted_proxies validated at load")
+            .with_header(config.forwarded_header),
     );
 
-    let app = Router::new()
-        // Health check available on all subdomains (not rate limited)
-        .route("/health", get(health_handler))
+    let shutdown = Shutdown::new();
+    spawn_shutdown_on_signal(shutdown.clone(), Duration::from_secs(config.shutdown.readiness_delay));
+
+    let state = AppState { tenant_manager, base_domains: config.base_domains.clone(), shutdown: shutdown.clone() };
+    let request_state = state.clone();
+    let redirect_state = state.clone();
+
+    let app_js_max_age = config.cache.app_js_max_age;
+    let favicon_max_age = config.cache.favicon_max_age;
+
+    let mut router = Router::new();
+    // Without an admin listener, metrics are served alongside the site
+    if config.metrics.enabled && config.metrics.listen.is_none() {
+        router = router.route("/metrics", get(metrics::metrics_handler));
+    }
+
+    let app = router
+        // Health check available on all subdomains (not rate limited by default)
+        .route("/health", get(routes::live_handler))
+        .route("/health/live", get(routes::live_handler))
+        .route("/health/ready", get(routes::ready_handler))
+        .route("/version", get(routes::version_handler))
         // Direct protocol routes
         .route("/", get(subdomain_aware_root))
         .route("/open", get(routes::open_handler))
         .route("/.well-known/srcuri.json", get(routes::wellknown_handler))
-        .route("/static/app.js", get(serve_app_js))
-        .route("/favicon.ico", get(serve_favicon))
-        .route("/favicon.svg", get(serve_favicon_svg))
+        .route("/api/v1/translate", get(routes::translate_handler).post(routes::translate_batch_handler))
+        .route("/static/app.js", get(move |host: Host| serve_app_js(host, app_js_max_age)))
+        .route("/static/srcuri_wasm.js", get(routes::srcuri_wasm::glue_handler))
+        .route("/static/srcuri_wasm_bg.wasm", get(routes::srcuri_wasm::module_handler))
+        .route("/favicon.ico", get(move |host: Host| serve_favicon(host, favicon_max_age)))
+        .route("/favicon.svg", get(move |host: Host| serve_favicon(host, favicon_max_age)))
         .fallback(get(subdomain_aware_fallback))
+        .layer(axum::middleware::from_fn_with_state(state.clone(), routes::tenant_guard_middleware))
         .with_state(state)
+        .layer(axum::middleware::from_fn_with_state(rate_limiter, ratelimit::rate_limit_middleware))
         .layer(axum::middleware::from_fn(csp::csp_middleware))
-        .layer(GovernorLayer { config: governor_config })
-        .layer(
-            CorsLayer::new()
-                .allow_origin(Any)
-                .allow_methods(Any)
-                .allow_headers(Any),
-        )
-        .layer(TraceLayer::new_for_http());
-
-    let port = std::env::var("PORT")
-        .ok()
-        .and_then(|p| p.parse().ok())
-        .unwrap_or(3000);
-
-    let addr = SocketAddr::from(([0, 0, 0, 0], port));
-
-    let listener = match tokio::net::TcpListener::bind(addr).await {
-        Ok(l) => l,
-        Err(e) => {
-            tracing::error!("Failed to bind to {}: {}", addr, e);
+        .layer(cors_layer(&config.cors.allowed_origins));
+    let app = sorcery_server::with_request_layers(app, request_state, trusted_proxies);
+
+    let mut servers = tokio::task::JoinSet::new();
+    let mut tcp_listen = config.listen.clone();
+    let mut local_listeners = Vec::new();
+
+    #[cfg(unix)]
+    {
+        use sorcery_server::listener::{self, Inherited};
+
+        let inherited = listener::inherited().unwrap_or_else(|e| {
+            tracing::error!("Failed to take sockets from LISTEN_FDS: {}", e);
             std::process::exit(1);
+        });
+        if !inherited.is_empty() {
+            // Socket activation: the service manager owns the plain-HTTP sockets
+            tracing::info!("Serving on {} sockets from LISTEN_FDS instead of `listen`", inherited.len());
+            tcp_listen.clear();
         }
-    };
+        for socket in inherited {
+            let stopped = shutdown.stopped();
+            match socket {
+                Inherited::Tcp(listener) => {
+                    local_listeners.push(format!("{} (inherited)", listener.local_addr().map_or("?".to_string(), |addr| addr.to_string())));
+                    let app = app.clone();
+                    servers.spawn(async move {
+                        axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+                            .with_graceful_shutdown(stopped)
+                            .await
+                    });
+                }
+                Inherited::Unix(listener) => {
+                    let path = listener.local_addr().ok().and_then(|addr| addr.as_pathname().map(|p| p.display().to_string()));
+                    local_listeners.push(format!("unix:{} (inherited)", path.as_deref().unwrap_or("?")));
+                    servers.spawn(listener::serve_unix(listener, app.clone(), stopped));
+                }
+            }
+        }
+
+        if let Some(path) = &config.unix_socket {
+            let mode = config.socket_mode().expect("validated with the config");
+            let listener = listener::bind_unix(path, mode).unwrap_or_else(|e| {
+                tracing::error!("Failed to bind Unix socket {}: {}", path.display(), e);
+                std::process::exit(1);
+            });
+            local_listeners.push(format!("unix:{} ({:o})", path.display(), mode));
+            servers.spawn(listener::serve_unix(listener, app.clone(), shutdown.stopped()));
+        }
+    }
+    #[cfg(not(unix))]
+    if config.unix_socket.is_some() {
+        tracing::warn!("unix_socket is only supported on Unix; ignoring it");
+    }
+
+    for &addr in &tcp_listen {
+        let listener = bind_or_exit(addr, "listener").await;
+        let app = app.clone();
+        let stopped = shutdown.stopped();
+        servers.spawn(async move {
+            axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+                .with_graceful_shutdown(stopped)
+                .await
+        });
+    }
 
+    if let Some(store) = &cert_store {
+        let rustls_config = axum_server::tls_rustls::RustlsConfig::from_config(store.clone().server_config());
+        for &addr in &config.tls.listen {
+            let listener = bind_or_exit(addr, "TLS listener").await.into_std().expect("tokio listener converts to std");
+            let handle = axum_server::Handle::new();
+            let stopped = shutdown.stopped();
+            let graceful = handle.clone();
+            tokio::spawn(async move {
+                stopped.await;
+                graceful.graceful_shutdown(None);
+            });
+            let server = axum_server::from_tcp_rustls(listener, rustls_config.clone())
+                .handle(handle)
+                .serve(app.clone().into_make_service_with_connect_info::<SocketAddr>());
+            servers.spawn(server);
+        }
+
+        let https_port = config.tls.listen[0].port();
+        for &addr in &config.tls.redirect_listen {
+            let listener = bind_or_exit(addr, "redirect listener").await;
+            let stopped = shutdown.stopped();
+            let redirect = tls::redirect_router(redirect_state.clone(), https_port);
+            servers.spawn(async move { axum::serve(listener, redirect).with_graceful_shutdown(stopped).await });
+        }
+    }
+
+    if let Some(admin_addr) = config.metrics.listen.filter(|_| config.metrics.enabled) {
+        let listener = bind_or_exit(admin_addr, "admin listener").await;
+        let admin = Router::new().route("/metrics", get(metrics::metrics_handler));
+        let stopped = shutdown.stopped();
+        servers.spawn(async move { axum::serve(listener, admin).with_graceful_shutdown(stopped).await });
+    }
+
+    let base_url = match (tcp_listen.first(), config.tls.listen.first()) {
+        (Some(addr), _) => Some(format!("http://localhost:{}", addr.port())),
+        (None, Some(addr)) => Some(format!("https://localhost:{}", addr.port())),
+        // Only Unix sockets, reached through the proxy
+        (None, None) => None,
+    };
     println!("\n  Sorcery Server running!\n");
-    println!("   Base URL:     http://localhost:{}", port);
-    println!("   Provider:     http://localhost:{}/github.com/owner/repo/blob/main/file.rs#L42", port);
-    println!("   Mirror:       http://localhost:{}/repo/src/lib.rs:42?branch=main", port);
-    println!("   Health:       http://localhost:{}/health\n", port);
+    for addr in &tcp_listen {
+        println!("   Listening:    {}", addr);
+    }
+    for listener in &local_listeners {
+        println!("   Listening:    {}", listener);
+    }
+    for addr in &config.tls.listen {
+        println!("   TLS:          {}", addr);
+    }
+    for addr in &config.tls.redirect_listen {
+        println!("   Redirect:     {} -> https", addr);
+    }
+    if let Some(store) = &cert_store {
+        let hosts = store.custom_hosts();
+        if !hosts.is_empty() {
+            println!("   Certificates: default + {}", hosts.join(", "));
+        }
+    }
+    if let Some(admin_addr) = config.metrics.listen.filter(|_| config.metrics.enabled) {
+        println!("   Metrics:      http://{}/metrics", admin_addr);
+    }
+    if let Some(base_url) = base_url {
+        println!("   Base URL:     {}", base_url);
+        println!("   Provider:     {}/github.com/owner/repo/blob/main/file.rs#L42", base_url);
+        println!("   Mirror:       {}/repo/src/lib.rs:42?branch=main", base_url);
+        println!("   Health:       {}/health/ready", base_url);
+    }
+    println!();
 
-    if let Err(e) = axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await {
-        tracing::error!("Server error: {}", e);
+    // Any listener stopping on its own is fatal; the others would otherwise keep serving a partial setup
+    let failed = tokio::select! {
+        Some(result) = servers.join_next() => Some(result),
+        _ = shutdown.stopped() => None,
+    };
+    if let Some(result) = failed {
+        match result {
+            Ok(Err(e)) => tracing::error!("Server error: {}", e),
+            Err(e) => tracing::error!("Server task failed: {}", e),
+            Ok(Ok(())) => tracing::error!("Server stopped unexpectedly"),
+        }
+        flush_telemetry(tracer_provider).await;
         std::process::exit(1);
     }
+
+    // Listeners have stopped accepting; let in-flight requests finish
+    let drain_timeout = Duration::from_secs(config.shutdown.drain_timeout);
+    let drained = tokio::time::timeout(drain_timeout, async {
+        while let Some(result) = servers.join_next().await {
+            if let Ok(Err(e)) = result {
+                tracing::error!("Server error while draining: {}", e);
+            }
+        }
+    })
+    .await;
+    if drained.is_err() {
+        tracing::warn!("Drain timeout of {}s reached, closing remaining connections", config.shutdown.drain_timeout);
+        servers.shutdown().await;
+    }
+
+    // Sockets from LISTEN_FDS belong to the service manager and stay in place
+    if let Some(path) = &config.unix_socket {
+        let _ = std::fs::remove_file(path);
+    }
+    flush_telemetry(tracer_provider).await;
+    tracing::info!("Shutdown complete");
+
+}
+
+/// `*` allows any origin; anything else is an explicit allowlist
+fn cors_layer(allowed_origins: &[String]) -> CorsLayer {
+    let origin = if allowed_origins.iter().any(|o| o == "*") {
+        AllowOrigin::any()
+    } else {
+        AllowOrigin::list(allowed_origins.iter().filter_map(|o| HeaderValue::from_str(o).ok()))
+    };
+    CorsLayer::new()
+        .allow_origin(origin)
+        .allow_methods(Any)
+        .allow_headers(Any)
+}
+
+/// Bind a TCP listener, exiting with a logged error if the address is unavailable
+async fn bind_or_exit(addr: SocketAddr, what: &str) -> tokio::net::TcpListener {
+    match tokio::net::TcpListener::bind(addr).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            tracing::error!("Failed to bind {} to {}: {}", what, addr, e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// On SIGTERM or SIGINT, fail readiness for `readiness_delay`, then stop the listeners
+fn spawn_shutdown_on_signal(shutdown: Shutdown, readiness_delay: Duration) {
+    tokio::spawn(async move {
+        shutdown::signal().await;
+        tracing::info!("Shutting down: failing readiness for {}s before draining", readiness_delay.as_secs());
+        shutdown.start_draining();
+        tokio::time::sleep(readiness_delay).await;
+        shutdown.stop();
+    });
+}
+
+/// Export any spans still buffered for the OTLP collector. Logs go straight
+/// to stdout and Prometheus metrics are pulled, so neither has a buffer.
+async fn flush_telemetry(tracer_provider: Option<opentelemetry_sdk::trace::SdkTracerProvider>) {
+    if let Some(provider) = tracer_provider {
+        // Shutdown blocks until the batch exporter has sent what it holds
+        let result = tokio::task::spawn_blocking(move || provider.shutdown()).await;
+        if let Ok(Err(e)) = result {
+            tracing::error!("Failed to flush spans: {}", e);
+        }
+    }
+}
+
+/// Reload tenant configs and TLS certificates on SIGHUP
+#[cfg(unix)]
+fn spawn_sighup_reload(tenant_manager: Arc<tenant::TenantManager>, cert_store: Option<Arc<tls::CertStore>>) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangup = match signal(SignalKind::hangup()) {
+        Ok(s) => s,
+        Err(e) => {
+            tracing::error!("Failed to install SIGHUP handler: {}", e);
+            return;
+        }
+    };
+    tokio::spawn(async move {
+        while hangup.recv().await.is_some() {
+            tracing::info!("SIGHUP received, reloading tenant configs");
+            tenant_manager.reload().await;
+            if let Some(store) = &cert_store {
+                store.reload();
+            }
+        }
+    });
 }
 
 async fn subdomain_aware_root(
     axum::extract::State(state): axum::extract::State<AppState>,
     Host(host): Host,
+    tenant: ResolvedTenant,
+    headers: HeaderMap,
     uri: Uri,
     query: Query<routes::passthrough::PassthroughQuery>,
 ) -> Response<Body> {
-    let mode = subdomain::detect_mode(&host, &uri);
-    match mode {
-        SubdomainMode::WwwRedirect => {
-            let new_uri = format!("https://{}{}", state.base_domain, uri.path_and_query().map(|pq| pq.as_str()).unwrap_or("/"));
-            Redirect::permanent(&new_uri).into_response()
-        }
+    match &tenant.mode {
+        SubdomainMode::WwwRedirect => www_redirect(&state, &host, &uri),
+        SubdomainMode::Reserved(_) => routes::LinkError::NotFound.into_response_for(&headers),
         SubdomainMode::DirectProtocol | SubdomainMode::EnterpriseTenant(_) => {
-            routes::root_handler(query).await.into_response()
+            routes::root_handler(tenant, headers, query).await.into_response()
         }
     }
 }
@@ -119,26 +397,28 @@ async fn subdomain_aware_root(
 async fn subdomain_aware_fallback(
     axum::extract::State(state): axum::extract::State<AppState>,
     Host(host): Host,
+    tenant: ResolvedTenant,
+    headers: HeaderMap,
     uri: Uri,
     query: axum::extract::Query<routes::passthrough::MirrorQuery>,
 ) -> Response<Body> {
-    let mode = subdomain::detect_mode(&host, &uri);
-    match mode {
-        SubdomainMode::WwwRedirect => {
-            let new_uri = format!("https://{}{}", state.base_domain, uri.path_and_query().map(|pq| pq.as_str()).unwrap_or("/"));
-            Redirect::permanent(&new_uri).into_response()
-        }
+    match &tenant.mode {
+        SubdomainMode::WwwRedirect => www_redirect(&state, &host, &uri),
+        SubdomainMode::Reserved(_) => routes::LinkError::NotFound.into_response_for(&headers),
         SubdomainMode::DirectProtocol | SubdomainMode::EnterpriseTenant(_) => {
-            routes::catchall_handler(uri, query).await.into_response()
+            routes::catchall_handler(axum::extract::State(state), tenant, headers, uri, query).await.into_response()
         }
     }
 }
 
-async fn health_handler() -> &'static str {
-    "OK"
+/// Permanent redirect from www.<domain> to <domain>, keeping path and query
+fn www_redirect(state: &AppState, host: &str, uri: &Uri) -> Response<Body> {
+    let target_host = subdomain::www_redirect_host(host, state.primary_domain());
+    let new_uri = format!("https://{}{}", target_host, uri.path_and_query().map(|pq| pq.as_str()).unwrap_or("/"));
+    Redirect::permanent(&new_uri).into_response()
 }
 
-async fn serve_app_js(Host(host): Host) -> Response<Body> {
+async fn serve_app_js(Host(host): Host, max_age: u64) -> Response<Body> {
     let content = include_str!("static/app.js");
     let host_without_port = host.split(':').next().unwrap_or(&host);
     let is_localhost = host_without_port == "localhost" || host_without_port == "127.0.0.1";
@@ -149,11 +429,11 @@ async fn serve_app_js(Host(host): Host) -> Response<Body> {
 
     if !is_localhost {
         let expires_time = SystemTime::now()
-            .checked_add(Duration::from_secs(86400))
+            .checked_add(Duration::from_secs(max_age))
             .unwrap_or(SystemTime::now());
         let expires_http = HttpDate::from(expires_time).to_string();
         builder = builder
-            .header(header::CACHE_CONTROL, "public, max-age=86400, immutable")
+            .header(header::CACHE_CONTROL, format!("public, max-age={}, immutable", max_age))
             .header(header::EXPIRES, expires_http);
     }
 
@@ -162,7 +442,7 @@ async fn serve_app_js(Host(host): Host) -> Response<Body> {
 
 const FAVICON_SVG: &str = r##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 512 512"><polygon points="52,428 87,463 328,230 293,195" fill="#1a1a1a"/><polygon points="370,30 398,117 485,145 398,173 370,260 342,173 255,145 342,117" fill="url(#g)"/><defs><radialGradient id="g" cx="370" cy="145" r="115" gradientUnits="userSpaceOnUse"><stop offset="0%" stop-color="#9333ea"/><stop offset="70%" stop-color="#c026d3"/><stop offset="100%" stop-color="#f59e0b"/></radialGradient></defs></svg>"##;
 
-async fn serve_favicon(Host(host): Host) -> Response<Body> {
+async fn serve_favicon(Host(host): Host, max_age: u64) -> Response<Body> {
     let host_without_port = host.split(':').next().unwrap_or(&host);
     let is_localhost = host_without_port == "localhost" || host_without_port == "127.0.0.1";
 
@@ -172,17 +452,13 @@ async fn serve_favicon(Host(host): Host) -> Response<Body> {
 
     if !is_localhost {
         let expires_time = SystemTime::now()
-            .checked_add(Duration::from_secs(7776000))
+            .checked_add(Duration::from_secs(max_age))
             .unwrap_or(SystemTime::now());
         let expires_http = HttpDate::from(expires_time).to_string();
         builder = builder
-            .header(header::CACHE_CONTROL, "public, max-age=7776000, immutable")
+            .header(header::CACHE_CONTROL, format!("public, max-age={}, immutable", max_age))
             .header(header::EXPIRES, expires_http);
     }
 
     builder.body(Body::from(FAVICON_SVG)).unwrap()
 }
-
-async fn serve_favicon_svg(Host(host): Host) -> Response<Body> {
-    serve_favicon(Host(host)).await
-}
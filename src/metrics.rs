@@ -0,0 +1,187 @@
+// Prometheus metrics
+// One process-wide registry, so code deep in the parsing and tenant layers can
+// count events without threading a handle through every call.
+
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use prometheus::{Encoder, HistogramOpts, HistogramVec, IntCounterVec, Opts, Registry, TextEncoder};
+use std::sync::OnceLock;
+use std::time::Instant;
+
+use crate::parsing::detect_provider;
+use crate::routes::passthrough::is_provider_path;
+
+pub struct Metrics {
+    registry: Registry,
+    /// Requests by route kind and status code
+    pub requests: IntCounterVec,
+    /// Request latency in seconds by route kind
+    pub request_duration: HistogramVec,
+    /// Provider URLs that failed to parse, by detected provider
+    pub parse_failures: IntCounterVec,
+    /// Mirror links rejected by parameter validation, by parameter
+    pub validation_rejections: IntCounterVec,
+    /// 429 responses by route group
+    pub rate_limited: IntCounterVec,
+    /// Tenant config lookups by outcome: hit, negative_hit or miss
+    pub tenant_cache: IntCounterVec,
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new_custom(Some("sorcery".to_string()), None).expect("valid prefix");
+
+        let requests = IntCounterVec::new(
+            Opts::new("http_requests_total", "HTTP requests by route kind and status"),
+            &["route", "status"],
+        )
+        .unwrap();
+        let request_duration = HistogramVec::new(
+            HistogramOpts::new("http_request_duration_seconds", "HTTP request latency by route kind"),
+            &["route"],
+        )
+        .unwrap();
+        let parse_failures = IntCounterVec::new(
+            Opts::new("parse_failures_total", "Provider URLs that failed to parse, by provider"),
+            &["provider"],
+        )
+        .unwrap();
+        let validation_rejections = IntCounterVec::new(
+            Opts::new("validation_rejections_total", "Mirror links rejected by validation, by parameter"),
+            &["param"],
+        )
+        .unwrap();
+        let rate_limited = IntCounterVec::new(
+            Opts::new("rate_limited_total", "Requests rejected by the rate limiter, by route group"),
+            &["group"],
+        )
+        .unwrap();
+        let tenant_cache = IntCounterVec::new(
+            Opts::new("tenant_cache_lookups_total", "Tenant config lookups by cache outcome"),
+            &["result"],
+        )
+        .unwrap();
+
+        registry.register(Box::new(requests.clone())).unwrap();
+        registry.register(Box::new(request_duration.clone())).unwrap();
+        registry.register(Box::new(parse_failures.clone())).unwrap();
+        registry.register(Box::new(validation_rejections.clone())).unwrap();
+        registry.register(Box::new(rate_limited.clone())).unwrap();
+        registry.register(Box::new(tenant_cache.clone())).unwrap();
+
+        Self { registry, requests, request_duration, parse_failures, validation_rejections, rate_limited, tenant_cache }
+    }
+
+    /// All metrics in the Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .expect("text encoding never fails");
+        String::from_utf8(buffer).expect("text encoding is UTF-8")
+    }
+}
+
+pub fn metrics() -> &'static Metrics {
+    static METRICS: OnceLock<Metrics> = OnceLock::new();
+    METRICS.get_or_init(Metrics::new)
+}
+
+/// Count a provider URL that failed to parse, labelled by `provider_label`
+pub fn record_parse_failure(url: &str) {
+    metrics().parse_failures.with_label_values(&[&provider_label(url)]).inc();
+}
+
+/// Lowercase provider name ("github", "gitlab", ...) that a URL's host or
+/// path suggests, or "unknown" when none matches. Used for both metric labels
+/// and log fields, so the two can be joined.
+pub fn provider_label(url: &str) -> String {
+    let with_scheme = if url.contains("://") { url.to_string() } else { format!("https://{}", url) };
+    url::Url::parse(&with_scheme)
+        .ok()
+        .and_then(|url| detect_provider(&url))
+        .map(|provider| format!("{:?}", provider).to_lowercase())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Coarse route kind for labelling, so per-link paths don't explode label cardinality
+pub fn route_kind(path: &str, query: Option<&str>) -> &'static str {
+    if path == "/" {
+        let passthrough = query.is_some_and(|q| {
+            url::form_urlencoded::parse(q.as_bytes()).any(|(key, _)| key == "remote")
+        });
+        return if passthrough { "passthrough" } else { "landing" };
+    }
+    if path.starts_with("/.well-known/") {
+        "wellknown"
+    } else if path.starts_with("/static/") || path == "/favicon.ico" || path == "/favicon.svg" {
+        "static"
+    } else if path == "/health" || path.starts_with("/health/") {
+        "health"
+    } else if path.starts_with("/api/") {
+        "api"
+    } else if path == "/open" {
+        "open"
+    } else if path == "/version" {
+        "version"
+    } else if path == "/metrics" {
+        "metrics"
+    } else if is_provider_path(path) {
+        "provider"
+    } else {
+        "mirror"
+    }
+}
+
+/// Count every request and time it, labelled by route kind
+pub async fn metrics_middleware(request: Request<Body>, next: Next) -> Response {
+    let route = route_kind(request.uri().path(), request.uri().query());
+    let start = Instant::now();
+    let response = next.run(request).await;
+
+    let m = metrics();
+    m.requests.with_label_values(&[route, response.status().as_str()]).inc();
+    m.request_duration.with_label_values(&[route]).observe(start.elapsed().as_secs_f64());
+    response
+}
+
+/// GET /metrics
+pub async fn metrics_handler() -> Response {
+    (
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4; charset=utf-8")],
+        metrics().render(),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_route_kinds() {
+        assert_eq!(route_kind("/", None), "landing");
+        assert_eq!(route_kind("/", Some("remote=github.com/a/b")), "passthrough");
+        assert_eq!(route_kind("/", Some("_subdomain=acme")), "landing");
+        assert_eq!(route_kind("/.well-known/srcuri.json", None), "wellknown");
+        assert_eq!(route_kind("/static/app.js", None), "static");
+        assert_eq!(route_kind("/favicon.svg", None), "static");
+        assert_eq!(route_kind("/github.com/acme/repo/blob/main/a.rs", None), "provider");
+        assert_eq!(route_kind("/myrepo/src/lib.rs:42", None), "mirror");
+    }
+
+    #[test]
+    fn test_render_includes_recorded_series() {
+        metrics().validation_rejections.with_label_values(&["branch"]).inc();
+        metrics().request_duration.with_label_values(&["mirror"]).observe(0.01);
+        let text = metrics().render();
+        assert!(text.contains("sorcery_validation_rejections_total{param=\"branch\"}"));
+        assert!(text.contains("# TYPE sorcery_http_request_duration_seconds histogram"));
+    }
+}
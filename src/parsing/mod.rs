@@ -1,2 +1,9 @@
 // Re-export from srcuri-core shared library
-pub use srcuri_core::{parse_remote_url, detect_provider, extract_path_line_suffix, ParseError, Provider, SrcuriTarget};
+pub use srcuri_core::{detect_provider, extract_path_line_suffix, ParseError, Provider, SrcuriTarget};
+
+/// `srcuri_core::parse_remote_url` in a `parse_url` span. The URL isn't
+/// recorded, since it can name private repositories.
+#[tracing::instrument(name = "parse_url", level = "debug", skip_all)]
+pub fn parse_remote_url(url: &str) -> Result<SrcuriTarget, ParseError> {
+    srcuri_core::parse_remote_url(url)
+}
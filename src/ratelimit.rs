@@ -0,0 +1,313 @@
+// Rate limiting per route group and tenant
+// Each group (health, static, pages, API) has its own policy, and clients are
+// tracked per tenant so one tenant's traffic can't exhaust another's budget.
+
+use axum::{
+    body::Body,
+    extract::{ConnectInfo, Request, State},
+    http::{header, HeaderMap, HeaderValue},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use governor::{
+    clock::{Clock, DefaultClock},
+    middleware::StateInformationMiddleware,
+    state::keyed::DefaultKeyedStateStore,
+    Quota,
+};
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::num::NonZeroU32;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::client_ip::ClientIp;
+use crate::config::{RateLimitConfig, RateLimitPolicy};
+use crate::metrics;
+use crate::routes::health;
+use crate::routes::api::ApiError;
+use crate::routes::LinkError;
+use crate::tenant::ResolvedTenant;
+
+/// Tenant ID and client IP
+type Key = (String, IpAddr);
+type KeyedLimiter = governor::RateLimiter<Key, DefaultKeyedStateStore<Key>, DefaultClock, StateInformationMiddleware>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RouteGroup {
+    Health,
+    Static,
+    Pages,
+    Api,
+}
+
+impl RouteGroup {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RouteGroup::Health => "health",
+            RouteGroup::Static => "static",
+            RouteGroup::Pages => "pages",
+            RouteGroup::Api => "api",
+        }
+    }
+
+    pub fn for_path(path: &str) -> Self {
+        if health::is_probe_path(path) {
+            RouteGroup::Health
+        } else if path.starts_with("/static/") || path == "/favicon.ico" || path == "/favicon.svg" {
+            RouteGroup::Static
+        } else if path.starts_with("/api/") {
+            RouteGroup::Api
+        } else {
+            RouteGroup::Pages
+        }
+    }
+}
+
+/// Rate-limit result for the access log, stored as a response extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateLimitOutcome {
+    /// The route group's policy is disabled
+    Exempt,
+    Allowed,
+    Limited,
+}
+
+impl RateLimitOutcome {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RateLimitOutcome::Exempt => "exempt",
+            RateLimitOutcome::Allowed => "allowed",
+            RateLimitOutcome::Limited => "limited",
+        }
+    }
+}
+
+/// Outcome of a rate-limit check, with the numbers for the `RateLimit-*` headers
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Decision {
+    Allowed { limit: u32, remaining: u32, reset: Duration },
+    Limited { limit: u32, retry_after: Duration },
+}
+
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    /// One limiter per group and policy; tenants sharing a policy share a
+    /// limiter but not buckets, since the key includes the tenant
+    limiters: Mutex<HashMap<(RouteGroup, RateLimitPolicy), Arc<KeyedLimiter>>>,
+    clock: DefaultClock,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            config,
+            limiters: Mutex::new(HashMap::new()),
+            clock: DefaultClock::default(),
+        }
+    }
+
+    fn server_policy(&self, group: RouteGroup) -> &RateLimitPolicy {
+        match group {
+            RouteGroup::Health => &self.config.health,
+            RouteGroup::Static => &self.config.static_assets,
+            RouteGroup::Pages => &self.config.pages,
+            RouteGroup::Api => &self.config.api,
+        }
+    }
+
+    /// Tenant ID and effective policy for a request. Unknown subdomains share
+    /// the "default" buckets so made-up hostnames can't mint fresh budgets.
+    fn policy_for(&self, group: RouteGroup, tenant: &ResolvedTenant) -> (String, RateLimitPolicy) {
+        let server_policy = self.server_policy(group).clone();
+        if matches!(group, RouteGroup::Health | RouteGroup::Static) {
+            return ("default".to_string(), server_policy);
+        }
+        let policy = tenant.config().and_then(|config| config.rate_limit.clone()).unwrap_or(server_policy);
+        (tenant.id().to_string(), policy)
+    }
+
+    /// Count one request from `ip` against `tenant`'s bucket for `group`
+    pub fn check(&self, group: RouteGroup, policy: &RateLimitPolicy, tenant: &str, ip: IpAddr) -> Decision {
+        let limiter = self.limiter(group, policy);
+        let limit = policy.burst_size;
+        let period = Duration::from_secs(policy.per_second);
+
+        match limiter.check_key(&(tenant.to_string(), ip)) {
+            Ok(snapshot) => {
+                let remaining = snapshot.remaining_burst_capacity();
+                Decision::Allowed { limit, remaining, reset: period * (limit - remaining) }
+            }
+            Err(not_until) => Decision::Limited { limit, retry_after: not_until.wait_time_from(self.clock.now()) },
+        }
+    }
+
+    fn limiter(&self, group: RouteGroup, policy: &RateLimitPolicy) -> Arc<KeyedLimiter> {
+        let mut limiters = self.limiters.lock().unwrap();
+        limiters
+            .entry((group, policy.clone()))
+            .or_insert_with(|| {
+                // Enabled policies are validated when the server config and
+                // tenant files load, so both values are non-zero
+                let quota = Quota::with_period(Duration::from_secs(policy.per_second))
+                    .expect("non-zero period")
+                    .allow_burst(NonZeroU32::new(policy.burst_size).expect("non-zero burst"));
+                Arc::new(governor::RateLimiter::keyed(quota).with_middleware::<StateInformationMiddleware>())
+            })
+            .clone()
+    }
+
+    /// Forget buckets that have fully refilled, to keep memory bounded
+    pub fn retain_recent(&self) {
+        for limiter in self.limiters.lock().unwrap().values() {
+            limiter.retain_recent();
+        }
+    }
+
+    /// Call `retain_recent` every `interval` in the background
+    pub fn spawn_cleanup(self: Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                self.retain_recent();
+            }
+        })
+    }
+}
+
+/// Apply the route group's policy, adding `RateLimit-*` headers to limited
+/// groups and answering with a 429 once a client's budget is spent
+pub async fn rate_limit_middleware(
+    State(limiter): State<Arc<RateLimiter>>,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    let group = RouteGroup::for_path(request.uri().path());
+    let resolved = request.extensions().get::<ResolvedTenant>().cloned().unwrap_or_else(ResolvedTenant::public);
+    let (tenant, policy) = limiter.policy_for(group, &resolved);
+    if !policy.enabled {
+        let mut response = next.run(request).await;
+        response.extensions_mut().insert(RateLimitOutcome::Exempt);
+        return response;
+    }
+
+    // `client_ip_middleware` normally runs first; without it fall back to the
+    // peer address, and without that (tests) every client shares one bucket
+    let ip = request
+        .extensions()
+        .get::<ClientIp>()
+        .map(|ClientIp(ip)| *ip)
+        .or_else(|| request.extensions().get::<ConnectInfo<SocketAddr>>().map(|ConnectInfo(addr)| addr.ip()))
+        .unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+
+    match limiter.check(group, &policy, &tenant, ip) {
+        Decision::Allowed { limit, remaining, reset } => {
+            let mut response = next.run(request).await;
+            set_rate_limit_headers(response.headers_mut(), limit, remaining, reset);
+            response.extensions_mut().insert(RateLimitOutcome::Allowed);
+            response
+        }
+        Decision::Limited { limit, retry_after } => {
+            tracing::debug!("Rate limited {} on tenant {} ({:?})", ip, tenant, group);
+            metrics::metrics().rate_limited.with_label_values(&[group.as_str()]).inc();
+            let mut response = too_many_requests(group, request.headers(), limit, retry_after);
+            response.extensions_mut().insert(RateLimitOutcome::Limited);
+            response
+        }
+    }
+}
+
+/// Whole seconds, rounded up so clients never retry too early
+fn ceil_secs(duration: Duration) -> u64 {
+    duration.as_secs() + u64::from(duration.subsec_nanos() > 0)
+}
+
+fn set_rate_limit_headers(headers: &mut HeaderMap, limit: u32, remaining: u32, reset: Duration) {
+    headers.insert("ratelimit-limit", HeaderValue::from(limit));
+    headers.insert("ratelimit-remaining", HeaderValue::from(remaining));
+    headers.insert("ratelimit-reset", HeaderValue::from(ceil_secs(reset)));
+}
+
+/// The API's own error JSON for `/api/*`; elsewhere the negotiated error
+/// page or `application/problem+json` every other link error gets
+fn too_many_requests(group: RouteGroup, request_headers: &HeaderMap, limit: u32, retry_after: Duration) -> Response {
+    let retry_secs = ceil_secs(retry_after).max(1);
+    let error = LinkError::RateLimited { retry_secs };
+
+    let mut response = if group == RouteGroup::Api {
+        ApiError::from(error).into_response()
+    } else {
+        error.into_response_for(request_headers)
+    };
+
+    let headers = response.headers_mut();
+    headers.insert(header::RETRY_AFTER, HeaderValue::from(retry_secs));
+    set_rate_limit_headers(headers, limit, 0, Duration::from_secs(retry_secs));
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter() -> RateLimiter {
+        RateLimiter::new(RateLimitConfig::default())
+    }
+
+    fn policy(burst_size: u32) -> RateLimitPolicy {
+        RateLimitPolicy { enabled: true, per_second: 60, burst_size }
+    }
+
+    #[test]
+    fn test_route_groups() {
+        assert_eq!(RouteGroup::for_path("/health"), RouteGroup::Health);
+        assert_eq!(RouteGroup::for_path("/health/ready"), RouteGroup::Health);
+        assert_eq!(RouteGroup::for_path("/health/repo/file.rs"), RouteGroup::Pages);
+        assert_eq!(RouteGroup::for_path("/healthy/repo/file.rs"), RouteGroup::Pages);
+        assert_eq!(RouteGroup::for_path("/static/app.js"), RouteGroup::Static);
+        assert_eq!(RouteGroup::for_path("/favicon.ico"), RouteGroup::Static);
+        assert_eq!(RouteGroup::for_path("/api/v1/translate"), RouteGroup::Api);
+        assert_eq!(RouteGroup::for_path("/github.com/acme/repo"), RouteGroup::Pages);
+        assert_eq!(RouteGroup::for_path("/"), RouteGroup::Pages);
+    }
+
+    #[test]
+    fn test_burst_then_limited() {
+        let limiter = limiter();
+        let ip = IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7));
+        let policy = policy(2);
+
+        assert!(matches!(
+            limiter.check(RouteGroup::Pages, &policy, "default", ip),
+            Decision::Allowed { limit: 2, remaining: 1, .. }
+        ));
+        assert!(matches!(
+            limiter.check(RouteGroup::Pages, &policy, "default", ip),
+            Decision::Allowed { remaining: 0, .. }
+        ));
+        let Decision::Limited { retry_after, .. } = limiter.check(RouteGroup::Pages, &policy, "default", ip) else {
+            panic!("third request should be limited");
+        };
+        assert!(retry_after > Duration::from_secs(50));
+    }
+
+    #[test]
+    fn test_buckets_are_separate_per_tenant_and_group() {
+        let limiter = limiter();
+        let ip = IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7));
+        let policy = policy(1);
+
+        assert!(matches!(limiter.check(RouteGroup::Pages, &policy, "acme", ip), Decision::Allowed { .. }));
+        assert!(matches!(limiter.check(RouteGroup::Pages, &policy, "acme", ip), Decision::Limited { .. }));
+        assert!(matches!(limiter.check(RouteGroup::Pages, &policy, "globex", ip), Decision::Allowed { .. }));
+        assert!(matches!(limiter.check(RouteGroup::Api, &policy, "acme", ip), Decision::Allowed { .. }));
+    }
+
+    #[test]
+    fn test_ceil_secs() {
+        assert_eq!(ceil_secs(Duration::from_millis(1)), 1);
+        assert_eq!(ceil_secs(Duration::from_secs(2)), 2);
+        assert_eq!(ceil_secs(Duration::from_millis(2001)), 3);
+    }
+}
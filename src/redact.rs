@@ -0,0 +1,163 @@
+// Privacy-preserving request logging
+// Enterprise links name private repositories, branches and files, so request
+// logs carry only the route kind, provider, tenant and a keyed hash of the
+// remote. A tenant can opt in to full paths with `log_full_paths`.
+
+use axum::http::Uri;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::sync::OnceLock;
+
+use crate::metrics::{provider_label, route_kind};
+use crate::parsing::parse_remote_url;
+
+static KEY: OnceLock<Vec<u8>> = OnceLock::new();
+
+/// Set the key for remote hashes. Without one, a random per-process key is
+/// used, so hashes still group requests but can't be correlated across restarts.
+/// Only the first call has an effect.
+pub fn init(key: Option<&str>) {
+    let _ = KEY.set(key.map(|k| k.as_bytes().to_vec()).unwrap_or_else(random_key));
+}
+
+fn key() -> &'static [u8] {
+    KEY.get_or_init(random_key)
+}
+
+/// 32 bytes straight from the OS random source
+fn random_key() -> Vec<u8> {
+    let mut key = vec![0u8; 32];
+    getrandom::fill(&mut key).expect("OS random source is available");
+    key
+}
+
+/// Short keyed hash of a remote. The remote is normalized first so
+/// `https://github.com/Acme/Repo.git` and `github.com/acme/repo` match.
+pub fn remote_hash(remote: &str) -> String {
+    let normalized = remote.trim().to_lowercase();
+    let normalized = normalized
+        .strip_prefix("https://")
+        .or_else(|| normalized.strip_prefix("http://"))
+        .unwrap_or(&normalized)
+        .trim_end_matches('/')
+        .trim_end_matches(".git");
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(key()).expect("HMAC accepts any key length");
+    mac.update(normalized.as_bytes());
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .take(6)
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Provider name and remote hash for a provider URL, without keeping the URL
+pub fn remote_summary(url: &str) -> (String, String) {
+    let remote_hash = match parse_remote_url(url) {
+        Ok(target) => remote_hash(&target.remote),
+        Err(_) => remote_hash(url),
+    };
+    (provider_label(url), remote_hash)
+}
+
+/// What request logs may say about a request, stored as a request extension
+/// by `access_log_middleware`
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogContext {
+    pub route: &'static str,
+    /// "default" on the public site
+    pub tenant: String,
+    pub provider: Option<String>,
+    pub remote_hash: Option<String>,
+    /// Path and query, only when the tenant opted in
+    pub full_path: Option<String>,
+}
+
+impl LogContext {
+    pub fn for_request(uri: &Uri, tenant: String, log_full_paths: bool) -> Self {
+        let path = uri.path();
+        let route = route_kind(path, uri.query());
+
+        let query_remote = uri.query().and_then(|q| {
+            url::form_urlencoded::parse(q.as_bytes())
+                .find(|(key, _)| key == "remote")
+                .map(|(_, value)| value.into_owned())
+        });
+        let remote = match route {
+            "provider" => Some(path.trim_start_matches('/').to_string()),
+            "passthrough" | "mirror" => query_remote,
+            _ => None,
+        };
+        let (provider, remote_hash) = match remote {
+            Some(remote) => {
+                let (provider, hash) = remote_summary(&remote);
+                (Some(provider), Some(hash))
+            }
+            None => (None, None),
+        };
+
+        Self {
+            route,
+            tenant,
+            provider,
+            remote_hash,
+            full_path: log_full_paths.then(|| uri.path_and_query().map(|pq| pq.to_string()).unwrap_or_default()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remote_hash_is_stable_and_normalized() {
+        let a = remote_hash("https://github.com/Acme/Billing.git");
+        assert_eq!(a, remote_hash("github.com/acme/billing"));
+        assert_eq!(a.len(), 12);
+        assert_ne!(a, remote_hash("github.com/acme/payroll"));
+    }
+
+    #[test]
+    fn test_random_key_is_fresh_and_full_length() {
+        let (a, b) = (random_key(), random_key());
+        assert_eq!(a.len(), 32);
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn test_mirror_context_hides_path() {
+        let uri: Uri = "/billing/src/secret.rs:42?branch=feature/layoffs&remote=github.com/acme/billing".parse().unwrap();
+        let context = LogContext::for_request(&uri, "acme".to_string(), false);
+        assert_eq!(context.route, "mirror");
+        assert_eq!(context.tenant, "acme");
+        assert_eq!(context.remote_hash, Some(remote_summary("github.com/acme/billing").1));
+        assert_eq!(context.full_path, None);
+        assert!(!format!("{:?}", context).contains("secret"));
+        assert!(!format!("{:?}", context).contains("layoffs"));
+    }
+
+    #[test]
+    fn test_summary_provider_matches_metric_label() {
+        assert_eq!(remote_summary("github.com/acme/billing/blob/main/a.rs").0, "github");
+        assert_eq!(remote_summary("https://github.com/acme").0, "github");
+        assert_eq!(remote_summary("example.invalid/nothing").0, "unknown");
+    }
+
+    #[test]
+    fn test_full_path_only_on_opt_in() {
+        let uri: Uri = "/billing/src/lib.rs:1".parse().unwrap();
+        let context = LogContext::for_request(&uri, "acme".to_string(), true);
+        assert_eq!(context.full_path.as_deref(), Some("/billing/src/lib.rs:1"));
+    }
+
+    #[test]
+    fn test_static_context_has_no_remote() {
+        let uri: Uri = "/static/app.js".parse().unwrap();
+        let context = LogContext::for_request(&uri, "default".to_string(), false);
+        assert_eq!(context.route, "static");
+        assert_eq!(context.remote_hash, None);
+        assert_eq!(context.provider, None);
+    }
+}
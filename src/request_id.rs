@@ -0,0 +1,75 @@
+// Request IDs
+// Each request gets an `X-Request-Id`: the caller's if it sent a sane one,
+// otherwise a fresh UUID. It is echoed in the response, written to the access
+// log and shown on error pages so users can quote it in bug reports.
+
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+
+pub static X_REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+
+/// The request's ID, stored as a request extension
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestId(pub String);
+
+tokio::task_local! {
+    static CURRENT: String;
+}
+
+/// ID of the request being handled on this task, or "" outside a request.
+/// Lets error pages show the ID without threading it through every handler.
+pub fn current() -> String {
+    CURRENT.try_with(String::clone).unwrap_or_default()
+}
+
+/// Propagated IDs are kept only if they're short and made of safe characters,
+/// since they end up in logs and HTML
+fn is_valid(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= 128
+        && id.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'))
+}
+
+pub async fn request_id_middleware(mut request: Request<Body>, next: Next) -> Response {
+    let id = request
+        .headers()
+        .get(&X_REQUEST_ID)
+        .and_then(|value| value.to_str().ok())
+        .filter(|id| is_valid(id))
+        .map(str::to_string)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+
+    request.extensions_mut().insert(RequestId(id.clone()));
+    let mut response = CURRENT.scope(id.clone(), next.run(request)).await;
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        response.headers_mut().insert(X_REQUEST_ID.clone(), value);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_ids() {
+        assert!(is_valid("7f3c2a9e-5b1d-4c8e-9a6f-2d4b8e1c3a7f"));
+        assert!(is_valid("lb-1234.abc_def:9"));
+        assert!(!is_valid(""));
+        assert!(!is_valid("<script>"));
+        assert!(!is_valid("has space"));
+        assert!(!is_valid(&"a".repeat(129)));
+    }
+
+    #[tokio::test]
+    async fn test_current_outside_request_is_empty() {
+        assert_eq!(current(), "");
+        let inside = CURRENT.scope("abc".to_string(), async { current() }).await;
+        assert_eq!(inside, "abc");
+    }
+}
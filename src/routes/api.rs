@@ -0,0 +1,279 @@
+use axum::{
+    extract::{rejection::JsonRejection, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Json, Response},
+};
+use serde::{Deserialize, Serialize};
+use crate::parsing::{parse_remote_url, SrcuriTarget};
+use crate::metrics;
+use crate::subdomain;
+use crate::AppState;
+use super::error::LinkError;
+use super::passthrough::{build_srcuri_url, is_provider_path, resolve_mirror_target, MirrorQuery};
+
+/// Maximum number of URLs accepted by a single batch request
+const MAX_BATCH_SIZE: usize = 100;
+
+#[derive(Deserialize)]
+pub struct TranslateQuery {
+    pub url: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct TranslateBatchRequest {
+    pub urls: Vec<String>,
+}
+
+/// JSON view of a `SrcuriTarget`
+#[derive(Debug, Serialize)]
+pub struct TargetJson {
+    pub remote: String,
+    pub repo_name: String,
+    pub ref_value: Option<String>,
+    pub file_path: Option<String>,
+    pub line: Option<u32>,
+    pub is_absolute: bool,
+    pub provider: String,
+}
+
+impl From<&SrcuriTarget> for TargetJson {
+    fn from(target: &SrcuriTarget) -> Self {
+        Self {
+            remote: target.remote.clone(),
+            repo_name: target.repo_name.clone(),
+            ref_value: target.ref_value.clone(),
+            file_path: target.file_path.clone(),
+            line: target.line,
+            is_absolute: target.is_absolute,
+            provider: target.provider_name().to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Translation {
+    pub target: TargetJson,
+    pub srcuri_url: String,
+    pub mirror_url: String,
+    pub view_url: Option<String>,
+}
+
+/// Everything the API can fail with. Each variant defines its `code`, which is
+/// stable and safe to match on, and its status; link failures reuse
+/// `LinkError`'s, so a link gets the same code and status from the API as from
+/// the page.
+#[derive(Debug)]
+pub enum ApiError {
+    /// No `url` query parameter
+    MissingUrl,
+    /// A batch body that isn't `{"urls": [...]}`
+    InvalidBody(String),
+    /// More URLs than `MAX_BATCH_SIZE`
+    BatchTooLarge,
+    /// A URL on this server that names no file or repository
+    NoLink { url: String },
+    /// A link that fails the way the page for it would
+    Link { error: LinkError, url: String },
+}
+
+/// Structured error body. `message` is human-readable and may change.
+#[derive(Debug, Serialize)]
+pub struct ErrorJson {
+    pub code: &'static str,
+    pub message: String,
+    pub url: String,
+}
+
+#[derive(Serialize)]
+struct ErrorBody {
+    error: ErrorJson,
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum BatchItem {
+    Ok(Translation),
+    Err(ErrorBody),
+}
+
+#[derive(Serialize)]
+struct BatchResponse {
+    results: Vec<BatchItem>,
+}
+
+impl ApiError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::MissingUrl => "missing_url",
+            ApiError::InvalidBody(_) => "invalid_body",
+            ApiError::BatchTooLarge => "batch_too_large",
+            ApiError::NoLink { .. } => "unparseable_url",
+            ApiError::Link { error, .. } => error.code(),
+        }
+    }
+
+    /// 400 for a missing or unreadable URL, 422 for an invalid value or
+    /// request body, 413 for an oversized batch, and otherwise the link's own
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ApiError::MissingUrl | ApiError::NoLink { .. } => StatusCode::BAD_REQUEST,
+            ApiError::InvalidBody(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            ApiError::BatchTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+            ApiError::Link { error, .. } => error.status(),
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            ApiError::MissingUrl => "The url query parameter is required".to_string(),
+            ApiError::InvalidBody(reason) => reason.clone(),
+            ApiError::BatchTooLarge => format!("A batch may contain at most {} URLs", MAX_BATCH_SIZE),
+            ApiError::NoLink { .. } => "URL does not point to a file or repository".to_string(),
+            ApiError::Link { error, .. } => error.message(),
+        }
+    }
+
+    fn body(&self) -> ErrorBody {
+        let url = match self {
+            ApiError::NoLink { url } | ApiError::Link { url, .. } => url.clone(),
+            ApiError::MissingUrl | ApiError::InvalidBody(_) | ApiError::BatchTooLarge => String::new(),
+        };
+        ErrorBody { error: ErrorJson { code: self.code(), message: self.message(), url } }
+    }
+}
+
+/// A link error outside any one URL, e.g. a spent rate limit
+impl From<LinkError> for ApiError {
+    fn from(error: LinkError) -> Self {
+        ApiError::Link { error, url: String::new() }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.status(), Json(self.body())).into_response()
+    }
+}
+
+/// GET /api/v1/translate?url=... - translate a single provider or srcuri URL
+pub async fn translate_handler(
+    State(state): State<AppState>,
+    Query(params): Query<TranslateQuery>,
+) -> Response {
+    let Some(url) = params.url.filter(|u| !u.trim().is_empty()) else {
+        return ApiError::MissingUrl.into_response();
+    };
+    match translate(&url, &state.base_domains) {
+        Ok(translation) => Json(translation).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+/// POST /api/v1/translate - translate up to `MAX_BATCH_SIZE` URLs in one call.
+/// Per-URL failures are reported inline so one bad link doesn't fail the batch.
+pub async fn translate_batch_handler(
+    State(state): State<AppState>,
+    request: Result<Json<TranslateBatchRequest>, JsonRejection>,
+) -> Response {
+    let request = match request {
+        Ok(Json(request)) => request,
+        Err(rejection) => return ApiError::InvalidBody(rejection.body_text()).into_response(),
+    };
+    if request.urls.len() > MAX_BATCH_SIZE {
+        return ApiError::BatchTooLarge.into_response();
+    }
+
+    let results = request
+        .urls
+        .iter()
+        .map(|url| match translate(url, &state.base_domains) {
+            Ok(translation) => BatchItem::Ok(translation),
+            Err(error) => BatchItem::Err(error.body()),
+        })
+        .collect();
+
+    Json(BatchResponse { results }).into_response()
+}
+
+/// Translate a provider URL, srcuri:// URL, or mirror URL on one of `base_domains`
+pub fn translate(input: &str, base_domains: &[String]) -> Result<Translation, ApiError> {
+    let primary_domain = base_domains.first().map(String::as_str).unwrap_or("srcuri.com");
+    let input = input.trim();
+
+    // srcuri://repo/path:42 maps directly onto a mirror path
+    if let Some(rest) = input.strip_prefix("srcuri://") {
+        return translate_mirror(input, &format!("/{}", rest), primary_domain);
+    }
+
+    let without_scheme = input
+        .strip_prefix("https://")
+        .or_else(|| input.strip_prefix("http://"))
+        .unwrap_or(input);
+    let (host, path) = match without_scheme.find('/') {
+        Some(idx) => without_scheme.split_at(idx),
+        None => (without_scheme, "/"),
+    };
+
+    if !subdomain::matches_base_domain(host, base_domains) {
+        return translate_remote(input, primary_domain);
+    }
+
+    // Link on our own domain: ?remote= passthrough, provider path, or mirror path
+    let (path_only, query) = path.split_once('?').unwrap_or((path, ""));
+    if path_only == "/" {
+        let remote = url::form_urlencoded::parse(query.as_bytes())
+            .find(|(k, _)| k == "remote")
+            .map(|(_, v)| v.into_owned());
+        return match remote {
+            Some(remote) => translate_remote(&remote, primary_domain),
+            None => Err(ApiError::NoLink { url: input.to_string() }),
+        };
+    }
+    if is_provider_path(path_only) {
+        return translate_remote(path.trim_start_matches('/'), primary_domain);
+    }
+    translate_mirror(input, path, primary_domain)
+}
+
+fn translate_remote(remote_url: &str, base_domain: &str) -> Result<Translation, ApiError> {
+    let target = parse_remote_url(remote_url).map_err(|e| {
+        metrics::record_parse_failure(remote_url);
+        let url = e.original_url.clone();
+        ApiError::Link { error: LinkError::UnparseableUrl(e), url }
+    })?;
+    Ok(build_translation(&target, base_domain))
+}
+
+fn translate_mirror(input: &str, path_and_query: &str, base_domain: &str) -> Result<Translation, ApiError> {
+    let without_fragment = path_and_query.split('#').next().unwrap_or(path_and_query);
+    let (path, query) = without_fragment.split_once('?').unwrap_or((without_fragment, ""));
+
+    let mut params = MirrorQuery { branch: None, remote: None };
+    for (key, value) in url::form_urlencoded::parse(query.as_bytes()) {
+        match key.as_ref() {
+            "branch" => params.branch = Some(value.into_owned()),
+            "remote" => params.remote = Some(value.into_owned()),
+            _ => {}
+        }
+    }
+
+    let target = resolve_mirror_target(path, params)
+        .map_err(|invalid| ApiError::Link { error: LinkError::InvalidParam(invalid), url: input.to_string() })?;
+    Ok(build_translation(&target, base_domain))
+}
+
+pub(crate) fn build_translation(target: &SrcuriTarget, base_domain: &str) -> Translation {
+    let mirror_url = target.to_mirror_url();
+    // Mirror URLs are site-relative; API consumers need an absolute link
+    let mirror_url = if mirror_url.starts_with('/') {
+        format!("https://{}{}", base_domain, mirror_url)
+    } else {
+        mirror_url
+    };
+    Translation {
+        target: TargetJson::from(target),
+        srcuri_url: build_srcuri_url(target),
+        mirror_url,
+        view_url: target.to_view_url(),
+    }
+}
@@ -0,0 +1,187 @@
+// Link errors
+// Every way a link can fail has a stable `code` and an HTTP status, so link
+// checkers and monitoring can tell a broken link from a good one. Browsers get
+// the HTML error page; other clients get an RFC 9457
+// `application/problem+json` document with the same code and message.
+
+use axum::{
+    http::{header, HeaderMap, StatusCode},
+    response::{Html, IntoResponse, Response},
+};
+use serde::Serialize;
+
+use super::negotiate::{self, Format};
+use super::passthrough::InvalidMirrorParam;
+use super::templates::{self, ErrorTemplate};
+use crate::parsing::ParseError;
+use crate::request_id;
+
+pub const PROBLEM_JSON: &str = "application/problem+json";
+
+#[derive(Debug)]
+pub enum LinkError {
+    /// A provider URL the parser doesn't understand
+    UnparseableUrl(ParseError),
+    /// A mirror link parameter that failed validation
+    InvalidParam(InvalidMirrorParam),
+    /// The tenant's allowlist doesn't include the remote
+    RemoteNotAllowed { tenant: String, remote: String },
+    /// A reserved subdomain, which serves nothing
+    NotFound,
+    /// A subdomain with no tenant config, under `strict` tenants
+    UnknownTenant(String),
+    /// The client's rate-limit budget is spent
+    RateLimited { retry_secs: u64 },
+}
+
+/// RFC 9457 problem details, plus the stable `code` and the request ID
+#[derive(Serialize)]
+struct Problem {
+    title: &'static str,
+    status: u16,
+    detail: String,
+    code: &'static str,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    url: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    request_id: String,
+}
+
+impl LinkError {
+    /// Stable identifier, safe to match on; the same codes the translate API uses
+    pub fn code(&self) -> &'static str {
+        match self {
+            LinkError::UnparseableUrl(_) => "unparseable_url",
+            LinkError::InvalidParam(invalid) => invalid.code(),
+            LinkError::RemoteNotAllowed { .. } => "remote_not_allowed",
+            LinkError::NotFound => "not_found",
+            LinkError::UnknownTenant(_) => "unknown_tenant",
+            LinkError::RateLimited { .. } => "rate_limited",
+        }
+    }
+
+    /// 400 for input that can't be read as a link, 422 for a well-formed link
+    /// with an invalid value, 403 and 404 for links this server won't serve,
+    /// and 429 once the client's rate limit is spent
+    pub fn status(&self) -> StatusCode {
+        match self {
+            LinkError::UnparseableUrl(_) => StatusCode::BAD_REQUEST,
+            LinkError::InvalidParam(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            LinkError::RemoteNotAllowed { .. } => StatusCode::FORBIDDEN,
+            LinkError::NotFound | LinkError::UnknownTenant(_) => StatusCode::NOT_FOUND,
+            LinkError::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+        }
+    }
+
+    fn page_title(&self) -> &'static str {
+        match self {
+            LinkError::UnparseableUrl(_) | LinkError::InvalidParam(_) => "Unable to Parse URL",
+            LinkError::RemoteNotAllowed { .. } => "Repository Not Allowed",
+            LinkError::NotFound => "Not Found",
+            LinkError::UnknownTenant(_) => "No Such Organization",
+            LinkError::RateLimited { .. } => "Too Many Requests",
+        }
+    }
+
+    /// Human-readable explanation; may change between releases
+    pub fn message(&self) -> String {
+        match self {
+            LinkError::UnparseableUrl(error) => error.message.clone(),
+            LinkError::InvalidParam(invalid) => invalid.message(),
+            LinkError::RemoteNotAllowed { tenant, remote } => format!(
+                "\"{}\" is not an approved repository for {}. Ask your administrator to add it to the allowed remotes.",
+                safe_display(remote, 100, |c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '/' | ':' | '@' | ' ')),
+                tenant
+            ),
+            LinkError::NotFound => "There is nothing here.".to_string(),
+            LinkError::UnknownTenant(name) => format!(
+                "There is no organization named \"{}\" on this server.",
+                safe_display(name, 63, |c| c.is_ascii_alphanumeric() || c == '-')
+            ),
+            LinkError::RateLimited { retry_secs } => format!(
+                "Too many requests. Try again in {} second{}.",
+                retry_secs,
+                if *retry_secs == 1 { "" } else { "s" }
+            ),
+        }
+    }
+
+    /// The offending URL, only when it's safe to link to
+    fn url(&self) -> String {
+        match self {
+            LinkError::UnparseableUrl(error) => safe_href_url(&error.original_url),
+            _ => String::new(),
+        }
+    }
+
+    /// Respond in the format the client asked for. There's no URL to list
+    /// for `text/uri-list` clients, so they get the problem document too.
+    pub fn into_response_for(self, headers: &HeaderMap) -> Response {
+        let response = match negotiate::preferred_format(headers) {
+            Format::Html => self.html_response(),
+            Format::Json | Format::UriList => self.problem_response(),
+        };
+        negotiate::vary_on_accept(response)
+    }
+
+    fn html_response(&self) -> Response {
+        let template = ErrorTemplate {
+            title: self.page_title().to_string(),
+            message: self.message(),
+            url: self.url(),
+            tenant_name: match self {
+                LinkError::RemoteNotAllowed { tenant, .. } => tenant.clone(),
+                _ => String::new(),
+            },
+            request_id: request_id::current(),
+        };
+        let html = templates::render(&template).unwrap_or_else(|e| format!("Template error: {}", e));
+        (self.status(), Html(html)).into_response()
+    }
+
+    fn problem_response(&self) -> Response {
+        let status = self.status();
+        let problem = Problem {
+            title: status.canonical_reason().unwrap_or("Error"),
+            status: status.as_u16(),
+            detail: self.message(),
+            code: self.code(),
+            url: self.url(),
+            request_id: request_id::current(),
+        };
+        let body = serde_json::to_string(&problem).expect("problem details serialize");
+        (status, [(header::CONTENT_TYPE, PROBLEM_JSON)], body).into_response()
+    }
+}
+
+/// Sanitize URL for use in href attribute - only allow http/https protocols
+/// Blocks javascript:, data:, vbscript: and other dangerous protocols
+pub(crate) fn safe_href_url(url: &str) -> String {
+    let lower = url.to_lowercase();
+    if lower.starts_with("http://") || lower.starts_with("https://") {
+        url.to_string()
+    } else {
+        String::new()
+    }
+}
+
+/// At most `max` characters of `value`, with anything `allowed` rejects shown as `?`
+fn safe_display(value: &str, max: usize, allowed: impl Fn(char) -> bool) -> String {
+    value.chars().take(max).map(|c| if allowed(c) { c } else { '?' }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_and_code() {
+        let error = LinkError::UnknownTenant("acme".to_string());
+        assert_eq!(error.status(), StatusCode::NOT_FOUND);
+        assert_eq!(error.code(), "unknown_tenant");
+        assert_eq!(LinkError::NotFound.status(), StatusCode::NOT_FOUND);
+        let error = LinkError::RemoteNotAllowed { tenant: "Acme".to_string(), remote: "evil.com/<x>".to_string() };
+        assert_eq!(error.status(), StatusCode::FORBIDDEN);
+        assert!(error.message().contains("evil.com/?x?"));
+    }
+}
@@ -0,0 +1,65 @@
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Json, Response},
+};
+use serde::Serialize;
+use crate::AppState;
+
+/// Whether `path` is one of the probe routes, which load balancers call with
+/// whatever Host they use
+pub fn is_probe_path(path: &str) -> bool {
+    matches!(path, "/health" | "/health/live" | "/health/ready")
+}
+
+/// GET /health and /health/live: the process is up and serving requests
+pub async fn live_handler() -> &'static str {
+    "OK"
+}
+
+#[derive(Serialize)]
+struct Readiness {
+    status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reason: Option<&'static str>,
+}
+
+/// GET /health/ready: 503 until the tenants directory is readable and the
+/// default tenant parses, so load balancers hold traffic from a broken
+/// instance, and again once shutdown starts, so they move traffic away.
+/// The `reason` is a fixed code; details are in the server log.
+pub async fn ready_handler(State(state): State<AppState>) -> Response {
+    let check = if state.shutdown.is_draining() {
+        Err("shutting_down")
+    } else {
+        state.tenant_manager.check_ready().await
+    };
+    match check {
+        Ok(()) => Json(Readiness { status: "ready", reason: None }).into_response(),
+        Err(reason) => {
+            tracing::warn!("Not ready: {}", reason);
+            (StatusCode::SERVICE_UNAVAILABLE, Json(Readiness { status: "not_ready", reason: Some(reason) }))
+                .into_response()
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct VersionInfo {
+    pub version: &'static str,
+    pub git_sha: &'static str,
+    pub build_time: &'static str,
+    pub srcuri_core: &'static str,
+    pub tenants: usize,
+}
+
+/// GET /version
+pub async fn version_handler(State(state): State<AppState>) -> Json<VersionInfo> {
+    Json(VersionInfo {
+        version: env!("CARGO_PKG_VERSION"),
+        git_sha: env!("SORCERY_GIT_SHA"),
+        build_time: env!("SORCERY_BUILD_TIME"),
+        srcuri_core: env!("SORCERY_SRCURI_CORE_REV"),
+        tenants: state.tenant_manager.loaded_count().await,
+    })
+}
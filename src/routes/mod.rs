@@ -1,10 +1,26 @@
+pub mod api;
+pub mod error;
+pub mod health;
+pub mod negotiate;
 pub mod open;
 pub mod passthrough;
 pub mod provider;
+pub mod srcuri_wasm;
 pub mod templates;
+pub mod tenant_guard;
 pub mod wellknown;
 
+/// First path segments the server routes itself. A workspace with one of
+/// these names can't be linked at its root (`/metrics` is the metrics
+/// endpoint, not the `metrics` workspace), so tenant files may not list one.
+pub const RESERVED_PATHS: &[&str] =
+    &["api", "favicon.ico", "favicon.svg", "health", "metrics", "open", "static", "version"];
+
+pub use api::{translate_handler, translate_batch_handler};
+pub use error::LinkError;
+pub use health::{live_handler, ready_handler, version_handler};
 pub use open::open_handler;
 pub use passthrough::{root_handler, catchall_handler, PassthroughQuery, MirrorQuery};
 pub use provider::provider_handler;
+pub use tenant_guard::tenant_guard_middleware;
 pub use wellknown::wellknown_handler;
@@ -0,0 +1,90 @@
+// Content negotiation
+// Link pages answer browsers with HTML and tools with JSON or a bare
+// `text/uri-list`, chosen from `Accept`. Responses that vary this way carry
+// `Vary: Accept` so caches keep the representations apart.
+
+use axum::{
+    http::{header, HeaderMap, HeaderValue},
+    response::Response,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Html,
+    Json,
+    UriList,
+}
+
+/// The representation `Accept` ranks highest. Browsers list `text/html` (or
+/// only `*/*`), so ties and a missing header get HTML.
+pub fn preferred_format(headers: &HeaderMap) -> Format {
+    let mut html = 0.0_f32;
+    let mut json = 0.0_f32;
+    let mut uri_list = 0.0_f32;
+    for range in headers.get_all(header::ACCEPT).iter().filter_map(|v| v.to_str().ok()).flat_map(|v| v.split(',')) {
+        let mut parts = range.split(';');
+        let media_type = parts.next().unwrap_or("").trim().to_ascii_lowercase();
+        let quality = parts
+            .filter_map(|param| param.trim().strip_prefix("q="))
+            .find_map(|q| q.trim().parse::<f32>().ok())
+            .unwrap_or(1.0);
+        match media_type.as_str() {
+            "text/html" | "*/*" => html = html.max(quality),
+            "text/*" => {
+                html = html.max(quality);
+                uri_list = uri_list.max(quality);
+            }
+            "application/json" | "application/problem+json" | "application/*" => json = json.max(quality),
+            "text/uri-list" => uri_list = uri_list.max(quality),
+            _ => {}
+        }
+    }
+
+    if json > html && json >= uri_list {
+        Format::Json
+    } else if uri_list > html && uri_list > json {
+        Format::UriList
+    } else {
+        Format::Html
+    }
+}
+
+/// Mark a negotiated response as depending on `Accept`
+pub fn vary_on_accept(mut response: Response) -> Response {
+    response.headers_mut().insert(header::VARY, HeaderValue::from_static("accept"));
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn accept(value: &str) -> Format {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ACCEPT, HeaderValue::from_str(value).unwrap());
+        preferred_format(&headers)
+    }
+
+    #[test]
+    fn test_browsers_get_html() {
+        assert_eq!(preferred_format(&HeaderMap::new()), Format::Html);
+        assert_eq!(accept("text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8"), Format::Html);
+        assert_eq!(accept("*/*"), Format::Html);
+        assert_eq!(accept("application/json, text/html"), Format::Html);
+    }
+
+    #[test]
+    fn test_tools_get_json() {
+        assert_eq!(accept("application/problem+json"), Format::Json);
+        assert_eq!(accept("application/json"), Format::Json);
+        assert_eq!(accept("text/html;q=0.5, application/json"), Format::Json);
+        assert_eq!(accept("application/json, */*;q=0.1"), Format::Json);
+    }
+
+    #[test]
+    fn test_uri_list() {
+        assert_eq!(accept("text/uri-list"), Format::UriList);
+        assert_eq!(accept("text/uri-list, */*;q=0.1"), Format::UriList);
+        assert_eq!(accept("text/*"), Format::Html);
+    }
+}
@@ -1,23 +1,25 @@
-use askama::Template;
 use axum::{
-    extract::Query,
-    http::{Uri, header, HeaderValue},
-    response::{Html, Redirect, IntoResponse, Response},
+    extract::{Query, State},
+    http::{HeaderMap, Uri, header, HeaderValue},
+    response::{Html, Json, Redirect, IntoResponse, Response},
 };
 use serde::Deserialize;
-use crate::parsing::{parse_remote_url, extract_path_line_suffix, ParseError, SrcuriTarget};
-use super::templates::{MirrorTemplate, ErrorTemplate};
-
-/// Sanitize URL for use in href attribute - only allow http/https protocols
-/// Blocks javascript:, data:, vbscript: and other dangerous protocols
-fn safe_href_url(url: &str) -> String {
-    let lower = url.to_lowercase();
-    if lower.starts_with("http://") || lower.starts_with("https://") {
-        url.to_string()
-    } else {
-        String::new()
-    }
-}
+use crate::metrics;
+use crate::redact;
+use crate::parsing::{parse_remote_url, extract_path_line_suffix, SrcuriTarget};
+use crate::tenant::config::TenantConfig;
+use crate::tenant::ResolvedTenant;
+use crate::AppState;
+use super::api::build_translation;
+use super::error::LinkError;
+use super::negotiate::{self, Format};
+use super::provider::render_provider_page;
+use super::srcuri_wasm::SrcuriWasmAssets;
+use super::templates::{self, LandingTemplate, MirrorTemplate};
+
+/// Build the srcuri:// protocol URL for a target. Shared with the browser
+/// through srcuri-wasm, so the pages build byte-identical links.
+pub(crate) use srcuri_wasm::build_srcuri_url;
 
 /// Validate branch names - allows chars found in real GitHub branch names
 fn is_valid_branch_name(name: &str) -> bool {
@@ -81,32 +83,74 @@ pub struct MirrorQuery {
 }
 
 /// Root handler: ?remote= for provider passthrough, else landing page
-pub async fn root_handler(Query(params): Query<PassthroughQuery>) -> Response {
+pub async fn root_handler(
+    tenant: ResolvedTenant,
+    headers: HeaderMap,
+    Query(params): Query<PassthroughQuery>,
+) -> Response {
     match params.remote {
-        Some(remote_url) => passthrough_redirect(&remote_url).into_response(),
-        None => Html(include_str!("../templates/landing.html").to_string()).into_response(),
+        Some(remote_url) => {
+            passthrough_redirect(&remote_url, tenant.config()).unwrap_or_else(|e| e.into_response_for(&headers))
+        }
+        None => render_landing_page(),
     }
 }
 
+fn render_landing_page() -> Response {
+    let template = LandingTemplate { wasm: SrcuriWasmAssets::default() };
+    Html(templates::render(&template).unwrap_or_else(|e| format!("Template error: {}", e))).into_response()
+}
+
 /// Catch-all handler for path-based URLs
 /// Detects whether path is a provider URL (passthrough) or workspace path (mirror)
 pub async fn catchall_handler(
+    State(state): State<AppState>,
+    tenant: ResolvedTenant,
+    headers: HeaderMap,
     uri: Uri,
     Query(params): Query<MirrorQuery>,
 ) -> Response {
     let path = uri.path().to_string();
+    let format = negotiate::preferred_format(&headers);
     // Check if this looks like a provider URL - serve HTML+JS interstitial
-    // (must be client-side to preserve URL fragments like #L42)
-    if is_provider_path(&path) {
-        serve_provider_page()
+    // (the link must be built client-side to keep URL fragments like #L42)
+    let result = if is_provider_path(&path) {
+        match tenant.config().map(|tenant| check_provider_path_allowed(tenant, &uri)) {
+            Some(Err(rejection)) => Err(rejection),
+            _ if format == Format::Html => Ok(render_provider_page(&uri)),
+            _ => serve_provider_target(&uri, format, tenant.link_host(state.primary_domain())),
+        }
     } else {
         // It's a workspace mirror path - serve the mirror page
-        serve_mirror_page(&path, params).into_response()
+        serve_mirror_page(&path, params, tenant.config(), format, tenant.link_host(state.primary_domain()))
+    };
+    match result {
+        Ok(response) => negotiate::vary_on_accept(response),
+        Err(e) => e.into_response_for(&headers),
+    }
+}
+
+/// The provider page resolves the final link client-side, so a tenant with an
+/// allowlist has the remote checked here first. The fragment never reaches the
+/// server, but the remote is fully determined by the path.
+fn check_provider_path_allowed(tenant: &TenantConfig, uri: &Uri) -> Result<(), LinkError> {
+    if tenant.allowed_remotes.is_none() {
+        return Ok(());
     }
+    let provider_url = uri
+        .path_and_query()
+        .map(|pq| pq.as_str())
+        .unwrap_or("/")
+        .trim_start_matches('/');
+    let target = parse_provider_url(provider_url)?;
+    if !tenant.allows_remote(&target.remote) {
+        return Err(remote_not_allowed(tenant, &target.remote));
+    }
+    Ok(())
 }
 
 /// Detect if path looks like a provider URL (github.com/..., gitlab.com/..., etc.)
-fn is_provider_path(path: &str) -> bool {
+pub(crate) fn is_provider_path(path: &str) -> bool {
     let normalized = path.trim_start_matches('/');
 
     // Check for https:// prefix (user included full URL)
@@ -160,51 +204,161 @@ fn is_provider_path(path: &str) -> bool {
     false
 }
 
-fn serve_provider_page() -> Response {
-    Html(include_str!("../templates/provider.html").to_string()).into_response()
+/// Provider path for clients that asked for JSON or a URI list. Browsers
+/// never send the fragment, but a tool can send it encoded as `%23`.
+fn serve_provider_target(uri: &Uri, format: Format, base_domain: &str) -> Result<Response, LinkError> {
+    let provider_url = uri.path_and_query().map(|pq| pq.as_str()).unwrap_or("/").trim_start_matches('/');
+    let target = parse_provider_url(&decode_line_fragment(provider_url))?;
+    Ok(render_target(&target, format, base_domain))
+}
+
+/// Decode a trailing `%23` line anchor (`%23L42`, `%23L10-L20`, `%23lines-5`)
+/// back into a fragment. Any other `%23` is part of a file or branch name, as
+/// in `src/C%23/Program.cs`, and stays encoded.
+fn decode_line_fragment(url: &str) -> String {
+    match url.rsplit_once("%23") {
+        Some((base, fragment)) if is_line_anchor(fragment) => format!("{}#{}", base, fragment),
+        _ => url.to_string(),
+    }
+}
+
+fn is_line_anchor(fragment: &str) -> bool {
+    let lines = fragment
+        .strip_prefix("lines-")
+        .or_else(|| fragment.strip_prefix('L'))
+        .unwrap_or(fragment);
+    lines.starts_with(|c: char| c.is_ascii_digit())
+        && lines.chars().all(|c| c.is_ascii_digit() || matches!(c, 'L' | 'C' | '-' | ':'))
+}
+
+/// Parse a provider URL, counting and logging failures
+pub(crate) fn parse_provider_url(remote_url: &str) -> Result<SrcuriTarget, LinkError> {
+    parse_remote_url(remote_url).map_err(|e| {
+        metrics::record_parse_failure(remote_url);
+        // The URL and parser message can name private repositories; log only the summary
+        let (provider, remote_hash) = redact::remote_summary(&e.original_url);
+        tracing::warn!(provider = %provider, remote_hash = %remote_hash, "Unable to parse provider URL");
+        LinkError::UnparseableUrl(e)
+    })
+}
+
+/// The resolved target in a machine-readable format: the translate API's
+/// JSON, or the `srcuri://` URL alone as a one-line `text/uri-list`
+fn render_target(target: &SrcuriTarget, format: Format, base_domain: &str) -> Response {
+    match format {
+        Format::Html => render_mirror_page(target),
+        Format::Json => Json(build_translation(target, base_domain)).into_response(),
+        Format::UriList => {
+            let body = format!("{}\r\n", build_srcuri_url(target));
+            ([(header::CONTENT_TYPE, "text/uri-list")], body).into_response()
+        }
+    }
 }
 
 /// For query-based passthrough (?remote=...), we can parse server-side
 /// since the fragment is URL-encoded in the query parameter
-fn passthrough_redirect(remote_url: &str) -> Response {
-    match parse_remote_url(remote_url) {
-        Ok(target) => {
-            let mirror_url = target.to_mirror_url();
-            Redirect::to(&mirror_url).into_response()
+fn passthrough_redirect(remote_url: &str, tenant: Option<&TenantConfig>) -> Result<Response, LinkError> {
+    let target = parse_provider_url(remote_url)?;
+    if let Some(tenant) = tenant {
+        if !tenant.allows_remote(&target.remote) {
+            return Err(remote_not_allowed(tenant, &target.remote));
+        }
+    }
+    let mirror_url = target.to_mirror_url();
+    Ok(Redirect::to(&mirror_url).into_response())
+}
+
+/// Serve the mirror page for srcuri:// protocol redirect, or the resolved
+/// target to clients that asked for JSON or a URI list
+fn serve_mirror_page(
+    path: &str,
+    params: MirrorQuery,
+    tenant: Option<&TenantConfig>,
+    format: Format,
+    base_domain: &str,
+) -> Result<Response, LinkError> {
+    let mut target = resolve_mirror_target(path, params).map_err(LinkError::InvalidParam)?;
+    if let Some(tenant) = tenant {
+        tenant.apply_workspace_defaults(&mut target);
+        if !target.remote.is_empty() && !tenant.allows_remote(&target.remote) {
+            return Err(remote_not_allowed(tenant, &target.remote));
+        }
+    }
+    Ok(render_target(&target, format, base_domain))
+}
+
+/// A mirror link parameter that failed validation
+#[derive(Debug)]
+pub struct InvalidMirrorParam {
+    pub param: &'static str,
+    pub value: String,
+}
+
+impl InvalidMirrorParam {
+    fn new(param: &'static str, value: &str) -> Self {
+        Self { param, value: value.to_string() }
+    }
+
+    /// Stable error code, shared by the error pages and the translate API
+    pub(crate) fn code(&self) -> &'static str {
+        match self.param {
+            "branch" => "invalid_branch",
+            "remote" => "invalid_remote",
+            "workspace" => "invalid_workspace",
+            "path" => "invalid_path",
+            _ => "invalid_parameter",
+        }
+    }
+
+    /// Human-readable explanation, same text as the HTML error page
+    pub(crate) fn message(&self) -> String {
+        if self.param == "branch" {
+            invalid_ref_message(self.param, &self.value)
+        } else {
+            invalid_param_message(self.param, &self.value)
         }
-        Err(e) => render_error(e).into_response(),
     }
 }
 
-/// Serve the mirror page for srcuri:// protocol redirect
-fn serve_mirror_page(path: &str, params: MirrorQuery) -> Response {
+/// Parse a mirror path and validate branch, remote, workspace and file path
+pub(crate) fn resolve_mirror_target(path: &str, params: MirrorQuery) -> Result<SrcuriTarget, InvalidMirrorParam> {
+    validate_mirror_target(path, params).inspect_err(|invalid| {
+        metrics::metrics().validation_rejections.with_label_values(&[invalid.param]).inc();
+    })
+}
+
+fn validate_mirror_target(path: &str, params: MirrorQuery) -> Result<SrcuriTarget, InvalidMirrorParam> {
     // Validate branch name if provided
     if let Some(ref branch) = params.branch {
         if !is_valid_branch_name(branch) {
-            return render_invalid_ref_error("branch", branch);
+            return Err(InvalidMirrorParam::new("branch", branch));
         }
     }
     // Validate remote URL if provided
     if let Some(ref remote) = params.remote {
         if !is_valid_remote_url(remote) {
-            return render_invalid_param_error("remote", remote);
+            return Err(InvalidMirrorParam::new("remote", remote));
         }
     }
     let target = parse_mirror_path(path, params);
     // Validate extracted repo name (workspace)
     if !target.repo_name.is_empty() && !target.is_absolute && !is_valid_workspace_name(&target.repo_name) {
-        return render_invalid_param_error("workspace", &target.repo_name);
+        return Err(InvalidMirrorParam::new("workspace", &target.repo_name));
     }
     // Validate file path (length limit, path traversal)
     if let Some(ref file_path) = target.file_path {
         if !is_valid_file_path(file_path) {
-            return render_invalid_param_error("path", file_path);
+            return Err(InvalidMirrorParam::new("path", file_path));
         }
     }
-    render_mirror_page(&target)
+    Ok(target)
+}
+
+fn remote_not_allowed(tenant: &TenantConfig, remote: &str) -> LinkError {
+    LinkError::RemoteNotAllowed { tenant: tenant.name.clone(), remote: remote.to_string() }
 }
 
-fn render_invalid_ref_error(param_type: &str, ref_name: &str) -> Response {
+fn invalid_ref_message(param_type: &str, ref_name: &str) -> String {
     let safe_display: String = ref_name
         .chars()
         .take(100)
@@ -217,24 +371,20 @@ fn render_invalid_ref_error(param_type: &str, ref_name: &str) -> Response {
         _ => "letters, numbers, and - _ . / @ , ( ) + # =",
     };
 
-    let template = ErrorTemplate {
-        message: format!(
-            "Invalid {} name: \"{}\". {} names may only contain {}",
-            param_type, safe_display, param_type, allowed_chars
-        ),
-        url: String::new(),
-    };
-    Html(template.render().unwrap_or_else(|e| format!("Template error: {}", e))).into_response()
+    format!(
+        "Invalid {} name: \"{}\". {} names may only contain {}",
+        param_type, safe_display, param_type, allowed_chars
+    )
 }
 
-fn render_invalid_param_error(param_type: &str, value: &str) -> Response {
+fn invalid_param_message(param_type: &str, value: &str) -> String {
     let safe_display: String = value
         .chars()
         .take(100)
         .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '/' | ':' | '@' | ' ') { c } else { '?' })
         .collect();
 
-    let message = match param_type {
+    match param_type {
         "remote" => format!(
             "Invalid remote URL: \"{}\". Remote URLs may only contain letters, numbers, and - _ . / : @",
             safe_display
@@ -252,17 +402,11 @@ fn render_invalid_param_error(param_type: &str, value: &str) -> Response {
             safe_display
         ),
         _ => format!("Invalid {}: \"{}\"", param_type, safe_display),
-    };
-
-    let template = ErrorTemplate {
-        message,
-        url: String::new(),
-    };
-    Html(template.render().unwrap_or_else(|e| format!("Template error: {}", e))).into_response()
+    }
 }
 
 /// Parse a mirror mode path like "repo/src/lib.rs:42" or "//absolute/path.rs:42"
-fn parse_mirror_path(path: &str, params: MirrorQuery) -> SrcuriTarget {
+pub(crate) fn parse_mirror_path(path: &str, params: MirrorQuery) -> SrcuriTarget {
     // Check for absolute path: starts with // (after the initial / from URI)
     // e.g., URI path "///Users/foo/file.txt" arrives as "///Users/foo/file.txt"
     // We need 3 slashes total for absolute paths: first slash is the URI path separator,
@@ -312,51 +456,17 @@ fn parse_mirror_path(path: &str, params: MirrorQuery) -> SrcuriTarget {
 }
 
 fn render_mirror_page(target: &SrcuriTarget) -> Response {
-    // Build srcuri:// URL
-    let mut srcuri = if target.is_absolute {
-        // Absolute path: srcuri:///path/to/file
-        let path = target.file_path.as_deref().unwrap_or("");
-        format!("srcuri://{}", path)
-    } else {
-        // Workspace path: srcuri://workspace/path/to/file
-        let mut s = format!("srcuri://{}/", target.repo_name);
-        if let Some(ref path) = target.file_path {
-            s.push_str(path);
-        }
-        s
-    };
-    if let Some(line) = target.line {
-        srcuri.push_str(&format!(":{}", line));
-    }
-
-    let mut query_parts = Vec::new();
-    if let Some(ref branch) = target.ref_value {
-        // URL-encode branch names to handle special characters like + # =
-        // Examples: "inputprocessing/c++" becomes "inputprocessing%2Fc%2B%2B"
-        //           "#pr470" becomes "%23pr470"
-        // Without encoding, + means space and # truncates at fragment delimiter.
-        let encoded: String = url::form_urlencoded::byte_serialize(branch.as_bytes()).collect();
-        query_parts.push(format!("branch={}", encoded));
-    }
-    if !target.remote.is_empty() {
-        // Always output with https:// prefix for git clone compatibility
-        query_parts.push(format!("remote=https://{}", target.remote));
-    }
-    if !query_parts.is_empty() {
-        srcuri.push('?');
-        srcuri.push_str(&query_parts.join("&"));
-    }
+    let srcuri = build_srcuri_url(target);
 
     // Build display info
     let display_path = target.file_path.as_deref().unwrap_or("");
     let display_line = target.line.map(|l| format!(":{}", l)).unwrap_or_default();
-    let display_branch = target.ref_value.as_deref().unwrap_or("main");
 
-    // Generate OG description
-    let og_description = if !display_path.is_empty() {
-        format!("{}{} on {} branch", display_path, display_line, display_branch)
-    } else {
-        format!("{} repository", target.repo_name)
+    // Generate OG description; only name a branch when the link (or tenant) supplies one
+    let og_description = match (display_path.is_empty(), target.ref_value.as_deref()) {
+        (false, Some(branch)) => format!("{}{} on {} branch", display_path, display_line, branch),
+        (false, None) => format!("{}{} in {}", display_path, display_line, target.repo_name),
+        (true, _) => format!("{} repository", target.repo_name),
     };
 
     // Generate view URL for remote provider (GitHub, GitLab, etc.)
@@ -373,7 +483,7 @@ fn render_mirror_page(target: &SrcuriTarget) -> Response {
         provider_name: provider_name.to_string(),
     };
 
-    let html = template.render().unwrap_or_else(|e| {
+    let html = templates::render(&template).unwrap_or_else(|e| {
         format!("Template error: {}", e)
     });
 
@@ -393,16 +503,6 @@ fn render_mirror_page(target: &SrcuriTarget) -> Response {
     response
 }
 
-fn render_error(error: ParseError) -> Html<String> {
-    let template = ErrorTemplate {
-        message: error.message,
-        url: safe_href_url(&error.original_url),
-    };
-    Html(template.render().unwrap_or_else(|e| {
-        format!("Template error: {}", e)
-    }))
-}
-
 /// Normalize remote URL to strip protocol prefix.
 /// Accepts both "github.com/owner/repo" and "https://github.com/owner/repo".
 /// Returns just "github.com/owner/repo" for consistent internal storage.
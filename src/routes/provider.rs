@@ -1,5 +1,96 @@
-use axum::response::Html;
+use axum::{
+    http::Uri,
+    response::{Html, IntoResponse, Response},
+};
+use crate::parsing::SrcuriTarget;
+use super::passthrough::parse_provider_url;
+use super::srcuri_wasm::SrcuriWasmAssets;
+use super::templates::{self, ProviderTemplate};
 
-pub async fn provider_handler() -> Html<&'static str> {
-    Html(include_str!("../templates/provider.html"))
+pub async fn provider_handler(uri: Uri) -> Response {
+    render_provider_page(&uri)
+}
+
+/// The provider interstitial for a path like `/github.com/owner/repo/blob/main/src/lib.rs`,
+/// with unfurl tags from a server-side parse. A path the server can't parse
+/// is counted and logged like any other parse failure, and still gets the
+/// page with generic tags; its script reports the error.
+pub(crate) fn render_provider_page(uri: &Uri) -> Response {
+    let provider_url = uri
+        .path_and_query()
+        .map(|pq| pq.as_str())
+        .unwrap_or("/")
+        .trim_start_matches('/');
+    let template = match parse_provider_url(provider_url) {
+        Ok(target) => provider_template(&target),
+        Err(_) => ProviderTemplate {
+            title: "Open in Editor".to_string(),
+            og_description: "Open this code file directly in your editor".to_string(),
+            file_path: String::new(),
+            branch: String::new(),
+            provider_name: String::new(),
+            wasm: SrcuriWasmAssets::default(),
+        },
+    };
+    Html(templates::render(&template).unwrap_or_else(|e| format!("Template error: {}", e))).into_response()
+}
+
+fn provider_template(target: &SrcuriTarget) -> ProviderTemplate {
+    let file_path = target.file_path.clone().unwrap_or_default();
+    let title = if file_path.is_empty() {
+        target.repo_name.clone()
+    } else {
+        format!("{} - {}", file_path, target.repo_name)
+    };
+    let og_description = match (file_path.is_empty(), target.ref_value.as_deref()) {
+        (false, Some(branch)) => format!("{} on {} branch of {}", file_path, branch, target.remote),
+        (false, None) => format!("{} in {}", file_path, target.remote),
+        (true, Some(branch)) => format!("{} on {} branch", target.remote, branch),
+        (true, None) => format!("{} repository", target.remote),
+    };
+    ProviderTemplate {
+        title,
+        og_description,
+        file_path,
+        branch: target.ref_value.clone().unwrap_or_default(),
+        provider_name: target.provider_name().to_string(),
+        wasm: SrcuriWasmAssets::default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http_body_util::BodyExt;
+
+    async fn page(uri: &str) -> String {
+        let response = render_provider_page(&uri.parse().unwrap());
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        String::from_utf8(body.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_unfurl_tags_from_server_side_parse() {
+        let html = page("/github.com/owner/repo/blob/main/src/lib.rs").await;
+        assert!(html.contains(r#"<meta property="og:title" content="src/lib.rs - repo">"#), "{}", html);
+        assert!(html.contains("src/lib.rs on main branch of github.com/owner/repo"));
+        assert!(html.contains(r#"<meta name="twitter:data1" content="main">"#));
+        assert!(html.contains(r#"<meta name="twitter:label2" content="Provider">"#));
+    }
+
+    #[tokio::test]
+    async fn test_unparseable_path_gets_generic_tags() {
+        let html = page("/github.com/owner").await;
+        assert!(html.contains(r#"<meta property="og:title" content="Open in Editor">"#));
+        assert!(!html.contains("twitter:label1"));
+        assert!(html.contains(">Loading...<"));
+    }
+
+    #[tokio::test]
+    async fn test_unparseable_path_counts_as_parse_failure() {
+        let failures = crate::metrics::metrics().parse_failures.with_label_values(&["github"]);
+        let before = failures.get();
+        page("/github.com/owner").await;
+        assert!(failures.get() > before);
+    }
 }
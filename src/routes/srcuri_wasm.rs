@@ -0,0 +1,52 @@
+// srcuri-wasm assets
+// The provider and landing pages parse links with srcuri-core compiled to
+// WebAssembly. build.rs builds the module and its wasm-bindgen glue; both are
+// embedded here and served under content-hashed URLs. Builds without the
+// `browser-wasm` feature have no module (`AVAILABLE` is false), and the pages
+// hand links to the server instead.
+
+use axum::{
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+
+include!(concat!(env!("OUT_DIR"), "/srcuri_wasm.rs"));
+
+const GLUE: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/wasm/srcuri_wasm.js"));
+const MODULE: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/wasm/srcuri_wasm_bg.wasm"));
+
+/// The URLs change whenever the content does, so a year is safe
+const CACHE_CONTROL: &str = "public, max-age=31536000, immutable";
+
+/// Where a page loads the parser from, for the `<script>` tag that does it
+#[derive(Debug, Clone, Copy)]
+pub struct SrcuriWasmAssets {
+    /// Whether this build includes the parser at all
+    pub available: bool,
+    pub glue_url: &'static str,
+    /// Subresource Integrity for the glue; the CSP allows it by this hash
+    pub glue_integrity: &'static str,
+    pub module_url: &'static str,
+}
+
+impl Default for SrcuriWasmAssets {
+    fn default() -> Self {
+        Self { available: AVAILABLE, glue_url: GLUE_URL, glue_integrity: GLUE_INTEGRITY, module_url: MODULE_URL }
+    }
+}
+
+/// `/static/srcuri_wasm.js`: the wasm-bindgen glue, defining `wasm_bindgen`
+pub async fn glue_handler() -> Response {
+    if !AVAILABLE {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    ([(header::CONTENT_TYPE, "application/javascript"), (header::CACHE_CONTROL, CACHE_CONTROL)], GLUE).into_response()
+}
+
+/// `/static/srcuri_wasm_bg.wasm`: the compiled parser
+pub async fn module_handler() -> Response {
+    if !AVAILABLE {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    ([(header::CONTENT_TYPE, "application/wasm"), (header::CACHE_CONTROL, CACHE_CONTROL)], MODULE).into_response()
+}
@@ -1,5 +1,13 @@
 use askama::Template;
 
+use super::srcuri_wasm::SrcuriWasmAssets;
+
+/// Render `template` in a `render_template` span named after its type
+pub fn render<T: Template>(template: &T) -> askama::Result<String> {
+    let name = std::any::type_name::<T>().rsplit("::").next().unwrap_or("template");
+    tracing::debug_span!("render_template", template = name).in_scope(|| template.render())
+}
+
 #[derive(Template)]
 #[template(path = "mirror.html")]
 pub struct MirrorTemplate {
@@ -12,9 +20,36 @@ pub struct MirrorTemplate {
     pub provider_name: String,
 }
 
+/// Provider-link interstitial. The fields come from parsing the path on the
+/// server, for unfurls; the page's script still resolves the full link,
+/// including the line from the fragment, with srcuri-wasm.
+#[derive(Template)]
+#[template(path = "provider.html")]
+pub struct ProviderTemplate {
+    pub title: String,
+    pub og_description: String,
+    /// Empty when the path didn't parse
+    pub file_path: String,
+    pub branch: String,
+    pub provider_name: String,
+    pub wasm: SrcuriWasmAssets,
+}
+
+/// Public-site landing page with the link generator, which parses with srcuri-wasm
+#[derive(Template)]
+#[template(path = "landing.html")]
+pub struct LandingTemplate {
+    pub wasm: SrcuriWasmAssets,
+}
+
 #[derive(Template)]
 #[template(path = "error.html")]
 pub struct ErrorTemplate {
+    pub title: String,
     pub message: String,
     pub url: String,
+    /// Enterprise tenant display name; empty on the public site
+    pub tenant_name: String,
+    /// `X-Request-Id` for bug reports; empty outside a request
+    pub request_id: String,
 }
@@ -0,0 +1,36 @@
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use crate::subdomain::SubdomainMode;
+use crate::tenant::ResolvedTenant;
+use crate::AppState;
+use super::error::LinkError;
+use super::health;
+
+/// Answer requests for reserved subdomains with a 404. In strict mode, do the
+/// same for subdomains without a tenant file instead of letting them behave
+/// like the default tenant.
+pub async fn tenant_guard_middleware(
+    State(state): State<AppState>,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    // Probes arrive with whatever Host the load balancer uses. Only the real
+    // routes are exempt; other /health/... paths are ordinary mirror links.
+    let path = request.uri().path();
+    if health::is_probe_path(path) || path == "/version" {
+        return next.run(request).await;
+    }
+
+    let resolved = request.extensions().get::<ResolvedTenant>().cloned().unwrap_or_else(ResolvedTenant::public);
+    match resolved.mode {
+        SubdomainMode::Reserved(_) => LinkError::NotFound.into_response_for(request.headers()),
+        SubdomainMode::EnterpriseTenant(name) if state.tenant_manager.is_strict() && resolved.config.is_none() => {
+            LinkError::UnknownTenant(name).into_response_for(request.headers())
+        }
+        _ => next.run(request).await,
+    }
+}
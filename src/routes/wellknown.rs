@@ -1,23 +1,34 @@
 use axum::{
     response::Json,
     extract::State,
-    http::HeaderMap,
     debug_handler,
 };
+use serde::Serialize;
 use crate::tenant::config::TenantConfig;
+use crate::tenant::ResolvedTenant;
 use crate::AppState;
 
-#[debug_handler]
-pub async fn wellknown_handler(
-    State(state): State<AppState>,
-    headers: HeaderMap,
-) -> Json<TenantConfig> {
-    let host = headers
-        .get("host")
-        .and_then(|h| h.to_str().ok())
-        .unwrap_or(&state.base_domain);
+/// The public part of a tenant config. Clients read the allowlist to know
+/// which links the tenant will open; the workspace registry names private
+/// repositories, and the rest is server configuration.
+#[derive(Serialize)]
+pub struct Discovery {
+    pub name: String,
+    pub default_remote: Option<String>,
+    pub allowed_remotes: Option<Vec<String>>,
+}
 
-    let subdomain = crate::tenant::TenantManager::extract_subdomain(host);
-    let config = state.tenant_manager.get_config(&subdomain).await;
-    Json(config)
+impl From<TenantConfig> for Discovery {
+    fn from(config: TenantConfig) -> Self {
+        Self { name: config.name, default_remote: config.default_remote, allowed_remotes: config.allowed_remotes }
+    }
+}
+
+#[debug_handler]
+pub async fn wellknown_handler(State(state): State<AppState>, tenant: ResolvedTenant) -> Json<Discovery> {
+    let config = match tenant.config {
+        Some(config) => TenantConfig::clone(&config),
+        None => state.tenant_manager.get_config("default").await,
+    };
+    Json(Discovery::from(config))
 }
@@ -0,0 +1,108 @@
+// Graceful shutdown
+// On SIGTERM or SIGINT the server first fails readiness so load balancers stop
+// sending traffic, then stops accepting connections and lets in-flight
+// requests finish within `[shutdown] drain_timeout`.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::sync::watch;
+
+/// Shared shutdown state: readiness reads `is_draining`, listeners wait on `stopped`
+#[derive(Clone)]
+pub struct Shutdown {
+    draining: Arc<AtomicBool>,
+    stop: Arc<watch::Sender<bool>>,
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self { draining: Arc::new(AtomicBool::new(false)), stop: Arc::new(watch::channel(false).0) }
+    }
+
+    /// Start failing readiness; requests are still served
+    pub fn start_draining(&self) {
+        self.draining.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::SeqCst)
+    }
+
+    /// Tell listeners to stop accepting connections. Implies draining.
+    pub fn stop(&self) {
+        self.start_draining();
+        self.stop.send_replace(true);
+    }
+
+    pub fn is_stopping(&self) -> bool {
+        *self.stop.borrow()
+    }
+
+    /// Resolves once `stop` has been called, for `with_graceful_shutdown`
+    pub fn stopped(&self) -> impl std::future::Future<Output = ()> + Send + 'static {
+        let mut stop = self.stop.subscribe();
+        async move {
+            let _ = stop.wait_for(|stopping| *stopping).await;
+        }
+    }
+}
+
+/// Resolves on the first SIGTERM or SIGINT
+pub async fn signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = terminate.recv() => tracing::info!("SIGTERM received"),
+                    _ = tokio::signal::ctrl_c() => tracing::info!("SIGINT received"),
+                }
+                return;
+            }
+            Err(e) => tracing::error!("Failed to install SIGTERM handler: {}", e),
+        }
+    }
+
+    if let Err(e) = tokio::signal::ctrl_c().await {
+        tracing::error!("Failed to listen for SIGINT: {}", e);
+        std::future::pending::<()>().await;
+    }
+    tracing::info!("SIGINT received");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_draining_comes_before_stop() {
+        let shutdown = Shutdown::new();
+        let stopped = tokio::spawn(shutdown.stopped());
+
+        shutdown.start_draining();
+        assert!(shutdown.is_draining());
+        assert!(!shutdown.is_stopping());
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!stopped.is_finished());
+
+        shutdown.stop();
+        tokio::time::timeout(Duration::from_secs(1), stopped).await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_stopped_after_stop_resolves_immediately() {
+        let shutdown = Shutdown::new();
+        shutdown.stop();
+        assert!(shutdown.is_draining());
+        tokio::time::timeout(Duration::from_secs(1), shutdown.stopped()).await.unwrap();
+    }
+}
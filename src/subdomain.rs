@@ -1,27 +1,85 @@
 use axum::http::Uri;
+use std::net::IpAddr;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum SubdomainMode {
     DirectProtocol,
     WwwRedirect,
     EnterpriseTenant(String),
+    /// Infrastructure hostname (api, admin, status, ...) that no tenant may claim
+    Reserved(String),
 }
 
-pub fn detect_mode(host: &str, uri: &Uri) -> SubdomainMode {
+/// Subdomains kept back for infrastructure unless overridden by configuration.
+/// "default" is reserved because `default.json` holds the public site's config.
+pub const DEFAULT_RESERVED_SUBDOMAINS: &[&str] = &[
+    "admin", "api", "app", "assets", "auth", "blog", "cdn", "dashboard", "default",
+    "docs", "ftp", "health", "help", "login", "mail", "metrics", "ns1", "ns2",
+    "smtp", "sso", "static", "status", "support", "www",
+];
+
+/// Whether `name` is a valid DNS label: 1-63 lowercase letters, digits and
+/// hyphens, not starting or ending with a hyphen
+pub fn is_valid_dns_label(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 63
+        && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        && !name.starts_with('-')
+        && !name.ends_with('-')
+}
+
+/// Resolve a Host header to a mode. `base_domains` are the apex domains the
+/// server answers on (e.g. "srcuri.com", "srcuri.co.uk"); tenants are the
+/// labels in front of them.
+pub fn detect_mode(host: &str, uri: &Uri, base_domains: &[String]) -> SubdomainMode {
     if is_localhost(host) {
         if let Some(override_mode) = check_query_override(uri) {
             return override_mode;
         }
     }
 
-    detect_mode_from_host(host)
+    detect_mode_from_host(host, base_domains)
+}
+
+/// Hostname part of a Host header: drops the port and IPv6 brackets
+/// ("[::1]:3000" becomes "::1")
+pub fn strip_port(host: &str) -> &str {
+    if let Some(bracketed) = host.strip_prefix('[') {
+        return bracketed.split(']').next().unwrap_or(bracketed);
+    }
+    // An unbracketed IPv6 literal has no port
+    if host.matches(':').count() > 1 {
+        return host;
+    }
+    host.split(':').next().unwrap_or(host)
+}
+
+/// Whether `host` is one of the base domains or a subdomain of one
+pub fn matches_base_domain(host: &str, base_domains: &[String]) -> bool {
+    let hostname = normalize_hostname(host);
+    base_domains.iter().any(|base| {
+        let base = normalize_hostname(base);
+        hostname == base || hostname.ends_with(&format!(".{}", base))
+    })
+}
+
+/// Where a www host redirects to: the same host without "www.". Hosts that
+/// don't start with "www." (the localhost override) go to `fallback_domain`.
+pub fn www_redirect_host<'a>(host: &'a str, fallback_domain: &'a str) -> &'a str {
+    strip_port(host)
+        .strip_prefix("www.")
+        .unwrap_or(fallback_domain)
+}
+
+fn normalize_hostname(host: &str) -> String {
+    strip_port(host).trim_end_matches('.').to_ascii_lowercase()
 }
 
 fn is_localhost(host: &str) -> bool {
-    let host_without_port = host.split(':').next().unwrap_or(host);
-    host_without_port == "localhost"
-        || host_without_port == "127.0.0.1"
-        || host_without_port == "::1"
+    let hostname = strip_port(host);
+    hostname == "localhost"
+        || hostname == "127.0.0.1"
+        || hostname == "::1"
 }
 
 fn check_query_override(uri: &Uri) -> Option<SubdomainMode> {
@@ -38,28 +96,34 @@ fn check_query_override(uri: &Uri) -> Option<SubdomainMode> {
     None
 }
 
-fn detect_mode_from_host(host: &str) -> SubdomainMode {
-    let host_without_port = host.split(':').next().unwrap_or(host);
-
-    let parts: Vec<&str> = host_without_port.split('.').collect();
+fn detect_mode_from_host(host: &str, base_domains: &[String]) -> SubdomainMode {
+    let hostname = normalize_hostname(host);
 
     // localhost or IP address - treat as direct protocol
-    if parts.len() == 1 || host_without_port.parse::<std::net::Ipv4Addr>().is_ok() {
-        return SubdomainMode::DirectProtocol;
-    }
-
-    // srcuri.com or just domain.tld (2 parts)
-    if parts.len() == 2 {
+    if !hostname.contains('.') || hostname.parse::<IpAddr>().is_ok() {
         return SubdomainMode::DirectProtocol;
     }
 
-    // subdomain.srcuri.com (3+ parts)
-    let subdomain = parts[0];
+    // Longest base domain first, so "links.corp.example.com" wins over "example.com"
+    let mut bases: Vec<String> = base_domains.iter().map(|b| normalize_hostname(b)).collect();
+    bases.sort_by_key(|b| std::cmp::Reverse(b.len()));
 
-    match subdomain {
-        "www" => SubdomainMode::WwwRedirect,
-        tenant => SubdomainMode::EnterpriseTenant(tenant.to_string()),
+    for base in &bases {
+        if hostname == *base {
+            return SubdomainMode::DirectProtocol;
+        }
+        if let Some(prefix) = hostname.strip_suffix(base.as_str()).and_then(|p| p.strip_suffix('.')) {
+            return match prefix {
+                "www" => SubdomainMode::WwwRedirect,
+                // Tenants are one label; "a.b.srcuri.com" is no tenant's host
+                tenant if tenant.contains('.') => SubdomainMode::DirectProtocol,
+                tenant => SubdomainMode::EnterpriseTenant(tenant.to_string()),
+            };
+        }
     }
+
+    // Not one of our domains (direct IP access, unknown Host) - public behavior
+    SubdomainMode::DirectProtocol
 }
 
 #[cfg(test)]
@@ -70,10 +134,14 @@ mod tests {
         s.parse().unwrap()
     }
 
+    fn bases() -> Vec<String> {
+        vec!["srcuri.com".to_string()]
+    }
+
     #[test]
     fn test_srcuri_com_is_direct() {
         assert_eq!(
-            detect_mode("srcuri.com", &uri("/")),
+            detect_mode("srcuri.com", &uri("/"), &bases()),
             SubdomainMode::DirectProtocol
         );
     }
@@ -81,7 +149,7 @@ mod tests {
     #[test]
     fn test_www_srcuri_com_redirects() {
         assert_eq!(
-            detect_mode("www.srcuri.com", &uri("/")),
+            detect_mode("www.srcuri.com", &uri("/"), &bases()),
             SubdomainMode::WwwRedirect
         );
     }
@@ -89,7 +157,7 @@ mod tests {
     #[test]
     fn test_tenant_subdomain() {
         assert_eq!(
-            detect_mode("fedex.srcuri.com", &uri("/")),
+            detect_mode("fedex.srcuri.com", &uri("/"), &bases()),
             SubdomainMode::EnterpriseTenant("fedex".to_string())
         );
     }
@@ -97,7 +165,7 @@ mod tests {
     #[test]
     fn test_localhost_is_direct() {
         assert_eq!(
-            detect_mode("localhost:3000", &uri("/")),
+            detect_mode("localhost:3000", &uri("/"), &bases()),
             SubdomainMode::DirectProtocol
         );
     }
@@ -105,7 +173,7 @@ mod tests {
     #[test]
     fn test_query_override_tenant() {
         assert_eq!(
-            detect_mode("localhost:3000", &uri("/?_subdomain=acme")),
+            detect_mode("localhost:3000", &uri("/?_subdomain=acme"), &bases()),
             SubdomainMode::EnterpriseTenant("acme".to_string())
         );
     }
@@ -113,7 +181,7 @@ mod tests {
     #[test]
     fn test_query_override_with_other_params() {
         assert_eq!(
-            detect_mode("localhost:3000", &uri("/path?foo=bar&_subdomain=acme&baz=qux")),
+            detect_mode("localhost:3000", &uri("/path?foo=bar&_subdomain=acme&baz=qux"), &bases()),
             SubdomainMode::EnterpriseTenant("acme".to_string())
         );
     }
@@ -121,7 +189,7 @@ mod tests {
     #[test]
     fn test_host_with_port() {
         assert_eq!(
-            detect_mode("acme.srcuri.com:443", &uri("/")),
+            detect_mode("acme.srcuri.com:443", &uri("/"), &bases()),
             SubdomainMode::EnterpriseTenant("acme".to_string())
         );
     }
@@ -129,7 +197,7 @@ mod tests {
     #[test]
     fn test_query_override_ignored_on_production() {
         assert_eq!(
-            detect_mode("srcuri.com", &uri("/?_subdomain=acme")),
+            detect_mode("srcuri.com", &uri("/?_subdomain=acme"), &bases()),
             SubdomainMode::DirectProtocol
         );
     }
@@ -137,8 +205,108 @@ mod tests {
     #[test]
     fn test_query_override_works_on_127_0_0_1() {
         assert_eq!(
-            detect_mode("127.0.0.1:3000", &uri("/?_subdomain=acme")),
+            detect_mode("127.0.0.1:3000", &uri("/?_subdomain=acme"), &bases()),
+            SubdomainMode::EnterpriseTenant("acme".to_string())
+        );
+    }
+
+    #[test]
+    fn test_multi_label_base_domain_is_direct() {
+        let bases = vec!["srcuri.co.uk".to_string()];
+        assert_eq!(detect_mode("srcuri.co.uk", &uri("/"), &bases), SubdomainMode::DirectProtocol);
+        assert_eq!(
+            detect_mode("acme.srcuri.co.uk", &uri("/"), &bases),
+            SubdomainMode::EnterpriseTenant("acme".to_string())
+        );
+        assert_eq!(detect_mode("www.srcuri.co.uk", &uri("/"), &bases), SubdomainMode::WwwRedirect);
+    }
+
+    #[test]
+    fn test_deep_base_domain() {
+        let bases = vec!["links.corp.example.com".to_string()];
+        assert_eq!(
+            detect_mode("links.corp.example.com", &uri("/"), &bases),
+            SubdomainMode::DirectProtocol
+        );
+        assert_eq!(
+            detect_mode("team.links.corp.example.com", &uri("/"), &bases),
+            SubdomainMode::EnterpriseTenant("team".to_string())
+        );
+    }
+
+    #[test]
+    fn test_several_base_domains_prefer_longest() {
+        let bases = vec!["example.com".to_string(), "links.example.com".to_string()];
+        assert_eq!(
+            detect_mode("links.example.com", &uri("/"), &bases),
+            SubdomainMode::DirectProtocol
+        );
+        assert_eq!(
+            detect_mode("acme.links.example.com", &uri("/"), &bases),
+            SubdomainMode::EnterpriseTenant("acme".to_string())
+        );
+        assert_eq!(
+            detect_mode("acme.example.com", &uri("/"), &bases),
+            SubdomainMode::EnterpriseTenant("acme".to_string())
+        );
+    }
+
+    #[test]
+    fn test_multi_label_prefix_is_not_a_tenant() {
+        assert_eq!(detect_mode("a.b.srcuri.com", &uri("/"), &bases()), SubdomainMode::DirectProtocol);
+        assert_eq!(detect_mode("www.acme.srcuri.com", &uri("/"), &bases()), SubdomainMode::DirectProtocol);
+    }
+
+    #[test]
+    fn test_unknown_host_is_direct() {
+        assert_eq!(
+            detect_mode("some.other.domain.org", &uri("/"), &bases()),
+            SubdomainMode::DirectProtocol
+        );
+    }
+
+    #[test]
+    fn test_ipv6_hosts() {
+        assert_eq!(detect_mode("[2001:db8::1]:443", &uri("/"), &bases()), SubdomainMode::DirectProtocol);
+        assert_eq!(detect_mode("[::1]", &uri("/"), &bases()), SubdomainMode::DirectProtocol);
+        assert_eq!(
+            detect_mode("[::1]:3000", &uri("/?_subdomain=acme"), &bases()),
+            SubdomainMode::EnterpriseTenant("acme".to_string())
+        );
+    }
+
+    #[test]
+    fn test_host_case_and_trailing_dot() {
+        assert_eq!(
+            detect_mode("ACME.SrcUri.com.", &uri("/"), &bases()),
             SubdomainMode::EnterpriseTenant("acme".to_string())
         );
     }
+
+    #[test]
+    fn test_dns_label_validation() {
+        assert!(is_valid_dns_label("acme"));
+        assert!(is_valid_dns_label("acme-corp2"));
+        assert!(!is_valid_dns_label(""));
+        assert!(!is_valid_dns_label("-acme"));
+        assert!(!is_valid_dns_label("acme-"));
+        assert!(!is_valid_dns_label("Acme"));
+        assert!(!is_valid_dns_label("acme.corp"));
+        assert!(!is_valid_dns_label("acme_corp"));
+        assert!(!is_valid_dns_label(&"a".repeat(64)));
+    }
+
+    #[test]
+    fn test_strip_port() {
+        assert_eq!(strip_port("srcuri.com:443"), "srcuri.com");
+        assert_eq!(strip_port("[::1]:3000"), "::1");
+        assert_eq!(strip_port("::1"), "::1");
+        assert_eq!(strip_port("srcuri.com"), "srcuri.com");
+    }
+
+    #[test]
+    fn test_www_redirect_host() {
+        assert_eq!(www_redirect_host("www.srcuri.co.uk:443", "srcuri.com"), "srcuri.co.uk");
+        assert_eq!(www_redirect_host("localhost:3000", "srcuri.com"), "srcuri.com");
+    }
 }
@@ -0,0 +1,99 @@
+// OpenTelemetry export
+// With `[telemetry] otlp_endpoint` set, `tracing` spans are also batched to an
+// OTLP collector over HTTP. A W3C `traceparent` header on the request makes the
+// request span a child of the caller's trace. Span fields follow the same
+// redaction as the logs: no raw paths or remotes.
+
+use axum::{body::Body, extract::Request, http::{HeaderMap, Method}};
+use opentelemetry::propagation::{Extractor, TextMapPropagator};
+use opentelemetry::trace::{TraceContextExt, TracerProvider as _};
+use opentelemetry_otlp::{ExporterBuildError, SpanExporter, WithExportConfig};
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::{SdkTracer, SdkTracerProvider};
+use opentelemetry_sdk::Resource;
+use tracing::Span;
+use tracing_opentelemetry::{OpenTelemetryLayer, OpenTelemetrySpanExt};
+use tracing_subscriber::filter::{Filtered, Targets};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+use crate::client_ip::ClientIp;
+use crate::redact::LogContext;
+use crate::request_id::RequestId;
+
+/// Tracer provider that batches spans to `{endpoint}/v1/traces` as OTLP/HTTP protobuf
+pub fn tracer_provider(endpoint: &str, service_name: &str) -> Result<SdkTracerProvider, ExporterBuildError> {
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_endpoint(format!("{}/v1/traces", endpoint.trim_end_matches('/')))
+        .build()?;
+
+    Ok(SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(Resource::builder().with_service_name(service_name.to_string()).build())
+        .build())
+}
+
+/// Subscriber layer that turns `tracing` spans into OpenTelemetry spans. It
+/// has its own filter, so the request span and its `debug` child spans are
+/// exported whatever `RUST_LOG` says the logs should show.
+pub fn layer<S>(provider: &SdkTracerProvider) -> Filtered<OpenTelemetryLayer<S, SdkTracer>, Targets, S>
+where
+    S: tracing::Subscriber + for<'span> LookupSpan<'span>,
+{
+    tracing_opentelemetry::layer()
+        .with_tracer(provider.tracer("sorcery-server"))
+        .with_filter(Targets::new().with_target("sorcery_server", tracing::Level::DEBUG))
+}
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|key| key.as_str()).collect()
+    }
+}
+
+/// Span for one request, for `TraceLayer::make_span_with`. It is created
+/// before the tenant is resolved, so tenant resolution nests inside it; the
+/// route and tenant fields are filled in later by `record_context`. Never
+/// carries the raw URI, and joins the caller's trace when the request has a
+/// valid `traceparent`.
+pub fn request_span(request: &Request<Body>) -> Span {
+    let client_ip = request.extensions().get::<ClientIp>().map(|ClientIp(ip)| ip.to_string());
+    let request_id = request.extensions().get::<RequestId>().map(|RequestId(id)| id.as_str());
+
+    let span = tracing::debug_span!(
+        "request",
+        otel.name = %request.method(),
+        otel.kind = "server",
+        request_id = request_id.unwrap_or("-"),
+        method = %request.method(),
+        route = tracing::field::Empty,
+        tenant = tracing::field::Empty,
+        provider = tracing::field::Empty,
+        remote_hash = tracing::field::Empty,
+        path = tracing::field::Empty,
+        client_ip = client_ip.as_deref().unwrap_or("-"),
+    );
+
+    let parent = TraceContextPropagator::new().extract(&HeaderExtractor(request.headers()));
+    // Fails only when no OpenTelemetry layer is installed, where there's nothing to join
+    let _ = span.set_parent(parent);
+    span
+}
+
+/// Fill in the request span's redacted route and tenant fields
+pub fn record_context(span: &Span, method: &Method, context: &LogContext) {
+    // `otel.name` can't be recorded once child spans have started the span
+    span.context().span().update_name(format!("{} {}", method, context.route));
+    span.record("route", context.route);
+    span.record("tenant", context.tenant.as_str());
+    span.record("provider", context.provider.as_deref().unwrap_or("-"));
+    span.record("remote_hash", context.remote_hash.as_deref().unwrap_or("-"));
+    span.record("path", context.full_path.as_deref().unwrap_or("-"));
+}
@@ -1,19 +1,60 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
+use crate::config::RateLimitPolicy;
+use crate::parsing::SrcuriTarget;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TenantConfig {
     pub name: String,
+    /// Base remote for workspaces not listed in `workspaces`; the workspace
+    /// name is appended, so "github.com/acme" maps `billing` to
+    /// "github.com/acme/billing"
     pub default_remote: Option<String>,
+    /// Remotes this tenant's links may point at. `None` allows any remote.
+    /// Entries are a host (`github.com`, `*.corp.example.com`), an org prefix
+    /// (`github.com/acme/*`) or a single repository (`github.com/acme/billing`).
     pub allowed_remotes: Option<Vec<String>>,
+    /// Known workspaces by name
+    #[serde(default)]
+    pub workspaces: BTreeMap<String, WorkspaceConfig>,
+    /// Hostnames CNAMEd to this server that serve this tenant, e.g. "code.acme.com"
+    #[serde(default)]
+    pub custom_domains: Vec<String>,
+    /// Redirect "www." in front of each custom domain to the bare domain
+    #[serde(default = "default_true")]
+    pub redirect_www: bool,
+    /// Replaces the server's page and API rate-limit policies on this tenant's hosts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<RateLimitPolicy>,
+    /// Log full request paths for this tenant. Off by default, since paths
+    /// name private repositories, branches and files.
+    #[serde(default)]
+    pub log_full_paths: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceConfig {
+    pub remote: String,
+    pub default_branch: Option<String>,
 }
 
 impl TenantConfig {
+    /// Parse a tenant file. A `rate_limit` the limiter can't apply is an
+    /// error, like a syntax error, so the tenant keeps its last good config.
     pub fn load_from_file(path: PathBuf) -> Result<Self, std::io::Error> {
         let content = std::fs::read_to_string(path)?;
-        serde_json::from_str(&content).map_err(|e| {
+        let config: Self = serde_json::from_str(&content).map_err(|e| {
             std::io::Error::new(std::io::ErrorKind::InvalidData, e)
-        })
+        })?;
+        if let Some(Err(message)) = config.rate_limit.as_ref().map(|policy| policy.validate()) {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("rate_limit: {}", message)));
+        }
+        Ok(config)
     }
 
     pub fn default_config() -> Self {
@@ -21,6 +62,217 @@ impl TenantConfig {
             name: "default".to_string(),
             default_remote: None,
             allowed_remotes: None,
+            workspaces: BTreeMap::new(),
+            custom_domains: Vec::new(),
+            redirect_www: true,
+            rate_limit: None,
+            log_full_paths: false,
+        }
+    }
+
+    /// Fill in the remote and branch of a workspace link that leaves them out,
+    /// from the workspace registry or `default_remote`
+    pub fn apply_workspace_defaults(&self, target: &mut SrcuriTarget) {
+        if target.is_absolute || target.repo_name.is_empty() {
+            return;
+        }
+        let workspace = self.workspaces.get(&target.repo_name);
+
+        if target.remote.is_empty() {
+            if let Some(workspace) = workspace {
+                target.remote = strip_scheme(&workspace.remote).trim_end_matches('/').to_string();
+            } else if let Some(ref base) = self.default_remote {
+                target.remote = format!("{}/{}", strip_scheme(base).trim_end_matches('/'), target.repo_name);
+            }
+        }
+        if target.ref_value.is_none() {
+            target.ref_value = workspace.and_then(|w| w.default_branch.clone());
+        }
+    }
+
+    /// Check a remote (with or without scheme) against `allowed_remotes`
+    pub fn allows_remote(&self, remote: &str) -> bool {
+        match self.allowed_remotes {
+            None => true,
+            Some(ref patterns) => {
+                let remote = normalize_remote(remote);
+                patterns.iter().any(|pattern| remote_matches(&normalize_remote(pattern), &remote))
+            }
+        }
+    }
+}
+
+fn strip_scheme(remote: &str) -> &str {
+    remote
+        .trim_start_matches("https://")
+        .trim_start_matches("http://")
+}
+
+/// Reduce a remote to lowercase "host/path" form: strips the scheme, rewrites
+/// scp-style "git@host:path", and drops any trailing "/" or ".git"
+fn normalize_remote(remote: &str) -> String {
+    let remote = remote.trim();
+    let remote = remote
+        .strip_prefix("https://")
+        .or_else(|| remote.strip_prefix("http://"))
+        .or_else(|| remote.strip_prefix("ssh://"))
+        .unwrap_or(remote);
+    let remote = match remote.strip_prefix("git@") {
+        Some(scp) => scp.replacen(':', "/", 1),
+        None => remote.to_string(),
+    };
+    let remote = remote.trim_end_matches('/');
+    let remote = remote.strip_suffix(".git").unwrap_or(remote);
+    remote.to_lowercase()
+}
+
+fn remote_matches(pattern: &str, remote: &str) -> bool {
+    let (pattern_host, pattern_path) = pattern.split_once('/').unwrap_or((pattern, ""));
+    let (remote_host, remote_path) = remote.split_once('/').unwrap_or((remote, ""));
+
+    let host_matches = match pattern_host.strip_prefix("*.") {
+        Some(suffix) => remote_host.ends_with(&format!(".{}", suffix)),
+        None => remote_host == pattern_host,
+    };
+    if !host_matches {
+        return false;
+    }
+
+    match pattern_path {
+        "" | "*" => true,
+        path => match path.strip_suffix("/*") {
+            Some(prefix) => remote_path.starts_with(&format!("{}/", prefix)),
+            None => remote_path == path,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tenant(allowed: &[&str]) -> TenantConfig {
+        TenantConfig {
+            allowed_remotes: Some(allowed.iter().map(|s| s.to_string()).collect()),
+            ..TenantConfig::default_config()
         }
     }
+
+    #[test]
+    fn test_invalid_rate_limit_is_rejected_at_load() {
+        let dir = crate::test_support::temp_dir();
+        let path = dir.path().join("acme.json");
+
+        std::fs::write(&path, r#"{"name": "Acme", "rate_limit": {"per_second": 0, "burst_size": 10}}"#).unwrap();
+        let err = TenantConfig::load_from_file(path.clone()).unwrap_err();
+        assert!(err.to_string().contains("per_second"), "{}", err);
+
+        std::fs::write(&path, r#"{"name": "Acme", "rate_limit": {"per_second": 1, "burst_size": 0}}"#).unwrap();
+        assert!(TenantConfig::load_from_file(path.clone()).is_err());
+
+        std::fs::write(&path, r#"{"name": "Acme", "rate_limit": {"enabled": false, "per_second": 0}}"#).unwrap();
+        assert!(TenantConfig::load_from_file(path).is_ok());
+    }
+
+    #[test]
+    fn test_no_allowlist_allows_everything() {
+        assert!(TenantConfig::default_config().allows_remote("github.com/anyone/anything"));
+    }
+
+    #[test]
+    fn test_host_pattern() {
+        let t = tenant(&["gitlab.acme.com"]);
+        assert!(t.allows_remote("gitlab.acme.com/team/app"));
+        assert!(t.allows_remote("https://gitlab.acme.com/team/app"));
+        assert!(!t.allows_remote("github.com/team/app"));
+    }
+
+    #[test]
+    fn test_wildcard_host_pattern() {
+        let t = tenant(&["*.corp.example.com"]);
+        assert!(t.allows_remote("git.corp.example.com/team/app"));
+        assert!(!t.allows_remote("corp.example.com.evil.io/team/app"));
+    }
+
+    #[test]
+    fn test_org_prefix_pattern() {
+        let t = tenant(&["github.com/acme/*"]);
+        assert!(t.allows_remote("github.com/acme/billing"));
+        assert!(t.allows_remote("github.com/ACME/billing"));
+        assert!(!t.allows_remote("github.com/acme-evil/billing"));
+        assert!(!t.allows_remote("github.com/other/billing"));
+    }
+
+    #[test]
+    fn test_exact_repo_pattern() {
+        let t = tenant(&["github.com/acme/billing"]);
+        assert!(t.allows_remote("https://github.com/acme/billing.git"));
+        assert!(t.allows_remote("git@github.com:acme/billing.git"));
+        assert!(!t.allows_remote("github.com/acme/billing-v2"));
+    }
+
+    #[test]
+    fn test_empty_allowlist_denies_everything() {
+        assert!(!tenant(&[]).allows_remote("github.com/acme/billing"));
+    }
+
+    fn workspace_target(repo_name: &str) -> SrcuriTarget {
+        SrcuriTarget {
+            remote: String::new(),
+            repo_name: repo_name.to_string(),
+            ref_value: None,
+            file_path: Some("src/lib.rs".to_string()),
+            line: Some(42),
+            is_absolute: false,
+        }
+    }
+
+    fn registry_tenant() -> TenantConfig {
+        let mut config = TenantConfig {
+            default_remote: Some("https://github.com/acme/".to_string()),
+            ..TenantConfig::default_config()
+        };
+        config.workspaces.insert(
+            "billing".to_string(),
+            WorkspaceConfig {
+                remote: "https://gitlab.acme.com/finance/billing".to_string(),
+                default_branch: Some("develop".to_string()),
+            },
+        );
+        config
+    }
+
+    #[test]
+    fn test_workspace_registry_fills_remote_and_branch() {
+        let mut target = workspace_target("billing");
+        registry_tenant().apply_workspace_defaults(&mut target);
+        assert_eq!(target.remote, "gitlab.acme.com/finance/billing");
+        assert_eq!(target.ref_value.as_deref(), Some("develop"));
+    }
+
+    #[test]
+    fn test_default_remote_appends_workspace_name() {
+        let mut target = workspace_target("payments");
+        registry_tenant().apply_workspace_defaults(&mut target);
+        assert_eq!(target.remote, "github.com/acme/payments");
+        assert_eq!(target.ref_value, None);
+    }
+
+    #[test]
+    fn test_explicit_values_are_kept() {
+        let mut target = workspace_target("billing");
+        target.remote = "github.com/acme/billing-fork".to_string();
+        target.ref_value = Some("hotfix".to_string());
+        registry_tenant().apply_workspace_defaults(&mut target);
+        assert_eq!(target.remote, "github.com/acme/billing-fork");
+        assert_eq!(target.ref_value.as_deref(), Some("hotfix"));
+    }
+
+    #[test]
+    fn test_absolute_paths_are_untouched() {
+        let mut target = workspace_target("");
+        target.is_absolute = true;
+        registry_tenant().apply_workspace_defaults(&mut target);
+        assert!(target.remote.is_empty());
+    }
 }
@@ -1,53 +1,683 @@
 pub mod config;
+pub mod resolve;
+pub mod validate;
 
+use axum::http::Uri;
 use config::TenantConfig;
+use lru::LruCache;
 use std::collections::HashMap;
-use std::path::PathBuf;
-use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant, SystemTime};
+use crate::metrics;
+use crate::subdomain::{self, SubdomainMode};
+pub use resolve::{resolve_tenant_middleware, ResolvedTenant};
 
 pub struct TenantManager {
-    configs: Arc<RwLock<HashMap<String, TenantConfig>>>,
+    /// The current configs and routing; replaced whole, never edited in place
+    snapshot: RwLock<Arc<Snapshot>>,
+    /// Subdomains with no tenant file, mapped to when the entry expires
+    unknown: Mutex<LruCache<String, Instant>>,
     tenants_dir: PathBuf,
+    options: TenantOptions,
+}
+
+/// Tenant configs and the custom-domain routes built from them, swapped in
+/// together so no request sees one reload's routing with another's configs
+#[derive(Default, Clone)]
+struct Snapshot {
+    /// Tenants backed by a file in `tenants_dir`; bounded by the directory contents
+    configs: HashMap<String, TenantConfig>,
+    /// Exact hostnames from tenants' `custom_domains`, rebuilt on reload
+    custom_domains: HashMap<String, SubdomainMode>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TenantOptions {
+    /// Maximum number of unknown subdomains remembered at once
+    pub negative_cache_size: usize,
+    /// How long an unknown subdomain is remembered before the disk is checked again
+    pub negative_ttl: Duration,
+    /// Serve a 404 for unknown tenants instead of treating them as the default tenant
+    pub strict: bool,
+    /// Subdomains no tenant may use; requests for them get a 404
+    pub reserved_subdomains: Vec<String>,
+    /// Apex domains the server answers on. No tenant may claim one of them,
+    /// or a subdomain of one, as a custom domain.
+    pub base_domains: Vec<String>,
+}
+
+impl Default for TenantOptions {
+    fn default() -> Self {
+        Self {
+            negative_cache_size: 1024,
+            negative_ttl: Duration::from_secs(60),
+            strict: false,
+            reserved_subdomains: subdomain::DEFAULT_RESERVED_SUBDOMAINS
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+            base_domains: vec!["srcuri.com".to_string()],
+        }
+    }
+}
+
+/// Name, modification time and size of each tenant file, used to detect changes
+type DirFingerprint = Vec<(String, Option<SystemTime>, u64)>;
+
+/// Tenant IDs come from file names and must be usable as a subdomain without
+/// shadowing a reserved name. `default` is the public site's config and is
+/// always allowed.
+pub fn check_tenant_id(name: &str, reserved: &[String]) -> Result<(), String> {
+    if name == "default" {
+        return Ok(());
+    }
+    if !subdomain::is_valid_dns_label(name) {
+        return Err(format!(
+            "tenant ID \"{}\" is not a valid DNS label (1-63 lowercase letters, digits or hyphens)",
+            name
+        ));
+    }
+    if reserved.iter().any(|r| r.eq_ignore_ascii_case(name)) {
+        return Err(format!("tenant ID \"{}\" is a reserved subdomain", name));
+    }
+    Ok(())
+}
+
+/// Custom domains must be hosts of their own. One under a base domain would
+/// hand the tenant the public site or another subdomain, reserved ones included.
+pub fn check_custom_domain(domain: &str, base_domains: &[String]) -> Result<(), String> {
+    if subdomain::matches_base_domain(domain, base_domains) {
+        return Err(format!("custom domain \"{}\" is a base domain or a subdomain of one", domain));
+    }
+    Ok(())
 }
 
 impl TenantManager {
     pub fn new(tenants_dir: PathBuf) -> Self {
+        Self::with_options(tenants_dir, TenantOptions::default())
+    }
+
+    pub fn with_options(tenants_dir: PathBuf, options: TenantOptions) -> Self {
+        let capacity = NonZeroUsize::new(options.negative_cache_size).unwrap_or(NonZeroUsize::MIN);
         Self {
-            configs: Arc::new(RwLock::new(HashMap::new())),
+            snapshot: RwLock::new(Arc::new(Snapshot::default())),
+            unknown: Mutex::new(LruCache::new(capacity)),
             tenants_dir,
+            options,
         }
     }
 
+    fn snapshot(&self) -> Arc<Snapshot> {
+        self.snapshot.read().unwrap().clone()
+    }
+
+    pub fn is_strict(&self) -> bool {
+        self.options.strict
+    }
+
+    pub fn is_reserved(&self, subdomain: &str) -> bool {
+        self.options.reserved_subdomains.iter().any(|r| r.eq_ignore_ascii_case(subdomain))
+    }
+
+    /// Config for `subdomain`, falling back to the default tenant when unknown
     pub async fn get_config(&self, subdomain: &str) -> TenantConfig {
-        {
-            let configs = self.configs.read().await;
-            if let Some(config) = configs.get(subdomain) {
-                return config.clone();
-            }
+        self.lookup(subdomain)
+            .await
+            .unwrap_or_else(TenantConfig::default_config)
+    }
+
+    /// Config for `subdomain`, or `None` if no valid tenant file exists.
+    /// Misses are remembered for `negative_ttl` so repeated requests for
+    /// made-up subdomains don't hit the disk.
+    #[tracing::instrument(name = "tenant_lookup", level = "debug", skip(self))]
+    pub async fn lookup(&self, subdomain: &str) -> Option<TenantConfig> {
+        // Never turn an arbitrary Host label or a reserved name into a file path
+        if self.check_tenant_id(subdomain).is_err() {
+            return None;
         }
 
-        let mut configs = self.configs.write().await;
-        if let Some(config) = configs.get(subdomain) {
-            return config.clone();
+        let cache = &metrics::metrics().tenant_cache;
+        if let Some(config) = self.snapshot().configs.get(subdomain) {
+            cache.with_label_values(&["hit"]).inc();
+            return Some(config.clone());
         }
 
+        if self.is_known_unknown(subdomain) {
+            cache.with_label_values(&["negative_hit"]).inc();
+            return None;
+        }
+        cache.with_label_values(&["miss"]).inc();
+
+        // Read the file off the async workers and without holding the lock;
+        // a concurrent lookup or reload that got there first wins
         let config_path = self.tenants_dir.join(format!("{}.json", subdomain));
-        let config = TenantConfig::load_from_file(config_path)
-            .unwrap_or_else(|_| TenantConfig::default_config());
-        configs.insert(subdomain.to_string(), config.clone());
-        config
+        let loaded = tokio::task::spawn_blocking(move || TenantConfig::load_from_file(config_path))
+            .await
+            .unwrap_or_else(|e| Err(std::io::Error::other(e)));
+        match loaded {
+            Ok(config) => {
+                let mut snapshot = self.snapshot.write().unwrap();
+                let configs = &mut Arc::make_mut(&mut snapshot).configs;
+                Some(configs.entry(subdomain.to_string()).or_insert(config).clone())
+            }
+            Err(e) => {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    tracing::error!("Failed to load tenant config for {}: {}", subdomain, e);
+                }
+                let expires = Instant::now() + self.options.negative_ttl;
+                self.unknown.lock().unwrap().put(subdomain.to_string(), expires);
+                None
+            }
+        }
+    }
+
+    /// Number of tenant configs currently loaded, including `default`
+    pub async fn loaded_count(&self) -> usize {
+        self.snapshot().configs.len()
     }
 
-    pub fn extract_subdomain(host: &str) -> String {
-        if let Some(subdomain) = host.split('.').next() {
-            if subdomain == "srcuri" || subdomain.contains(':') {
+    /// Whether the server can serve tenants: the tenants directory must be
+    /// readable and `default.json`, if present, must load. Without a
+    /// `default.json` the built-in default config is used. Failures are a
+    /// stable code for the unauthenticated readiness probe; the paths and
+    /// parse errors behind them are only logged.
+    pub async fn check_ready(&self) -> Result<(), &'static str> {
+        let tenants_dir = self.tenants_dir.clone();
+        tokio::task::spawn_blocking(move || {
+            if let Err(e) = std::fs::read_dir(&tenants_dir) {
+                tracing::warn!("Tenants directory {} is unreadable: {}", tenants_dir.display(), e);
+                return Err("tenants_dir_unreadable");
+            }
+            let default_path = tenants_dir.join("default.json");
+            match TenantConfig::load_from_file(default_path.clone()) {
+                Ok(_) => Ok(()),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+                Err(e) => {
+                    tracing::warn!("{} is invalid: {}", default_path.display(), e);
+                    Err("default_tenant_invalid")
+                }
+            }
+        })
+        .await
+        .unwrap_or(Err("tenants_dir_unreadable"))
+    }
+
+    pub fn check_tenant_id(&self, name: &str) -> Result<(), String> {
+        check_tenant_id(name, &self.options.reserved_subdomains)
+    }
+
+    /// Whether `subdomain` has an unexpired negative cache entry
+    fn is_known_unknown(&self, subdomain: &str) -> bool {
+        let mut unknown = self.unknown.lock().unwrap();
+        match unknown.get(subdomain) {
+            Some(expires) if *expires > Instant::now() => true,
+            Some(_) => {
+                unknown.pop(subdomain);
+                false
+            }
+            None => false,
+        }
+    }
+
+    /// Re-read every tenant file and swap in the new configs and custom
+    /// domains as one snapshot. A file that fails to parse keeps its last good
+    /// config; if the directory itself can't be read, nothing changes. The
+    /// directory is read off the async workers.
+    pub async fn reload(&self) {
+        let previous = self.snapshot();
+        let tenants_dir = self.tenants_dir.clone();
+        let options = self.options.clone();
+        let loaded = tokio::task::spawn_blocking(move || load_snapshot(&tenants_dir, &options, &previous)).await;
+        let Ok(Some(next)) = loaded else {
+            return;
+        };
+
+        tracing::info!("Loaded {} tenant configs from {}", next.configs.len(), self.tenants_dir.display());
+        *self.snapshot.write().unwrap() = Arc::new(next);
+        self.unknown.lock().unwrap().clear();
+    }
+
+    /// Poll the tenants directory and reload whenever a tenant file is added,
+    /// removed or modified
+    pub fn watch(self: Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut last = self.fingerprint().await;
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+            loop {
+                ticker.tick().await;
+                let current = self.fingerprint().await;
+                if current != last {
+                    tracing::info!("Tenant configs changed on disk, reloading");
+                    self.reload().await;
+                    last = current;
+                }
+            }
+        })
+    }
+
+    async fn fingerprint(&self) -> DirFingerprint {
+        let tenants_dir = self.tenants_dir.clone();
+        tokio::task::spawn_blocking(move || fingerprint(&tenants_dir)).await.unwrap_or_default()
+    }
+
+    /// Mode for a request: tenant custom domains by exact host first, then
+    /// subdomains of the base domains
+    #[tracing::instrument(name = "detect_subdomain", level = "debug", skip_all)]
+    pub fn detect_mode(&self, host: &str, uri: &Uri, base_domains: &[String]) -> SubdomainMode {
+        let hostname = subdomain::strip_port(host).trim_end_matches('.').to_ascii_lowercase();
+        if let Some(mode) = self.snapshot().custom_domains.get(&hostname) {
+            return mode.clone();
+        }
+        match subdomain::detect_mode(host, uri, base_domains) {
+            SubdomainMode::EnterpriseTenant(name) if self.is_reserved(&name) => SubdomainMode::Reserved(name),
+            mode => mode,
+        }
+    }
+
+    /// Mode and tenant config for a request, for `resolve_tenant_middleware`
+    pub async fn resolve(&self, host: &str, uri: &Uri, base_domains: &[String]) -> ResolvedTenant {
+        let mode = self.detect_mode(host, uri, base_domains);
+        let config = match &mode {
+            SubdomainMode::EnterpriseTenant(name) => self.lookup(name).await.map(Arc::new),
+            SubdomainMode::DirectProtocol | SubdomainMode::WwwRedirect | SubdomainMode::Reserved(_) => None,
+        };
+        let hostname = subdomain::strip_port(host).trim_end_matches('.').to_ascii_lowercase();
+        let known = self.is_known_host(&hostname, base_domains);
+        ResolvedTenant { mode, config, host: known.then_some(hostname) }
+    }
+
+    /// Whether the server answers for `hostname`: a base domain, a subdomain
+    /// of one, or a tenant's custom domain. Expects a lowercase hostname
+    /// without port.
+    pub fn is_known_host(&self, hostname: &str, base_domains: &[String]) -> bool {
+        subdomain::matches_base_domain(hostname, base_domains) || self.snapshot().custom_domains.contains_key(hostname)
+    }
+
+    /// Tenant file name a Host resolves to: the tenant subdomain, or
+    /// "default" for the public site
+    pub fn tenant_id_for_host(&self, host: &str, base_domains: &[String]) -> String {
+        match self.detect_mode(host, &Uri::from_static("/"), base_domains) {
+            SubdomainMode::EnterpriseTenant(name) => name,
+            SubdomainMode::DirectProtocol | SubdomainMode::WwwRedirect | SubdomainMode::Reserved(_) => {
                 "default".to_string()
-            } else {
-                subdomain.to_string()
             }
-        } else {
-            "default".to_string()
         }
     }
 }
+
+/// Every valid tenant file in `tenants_dir`, falling back to `previous` for
+/// files that fail to load, or `None` if the directory can't be read
+fn load_snapshot(tenants_dir: &Path, options: &TenantOptions, previous: &Snapshot) -> Option<Snapshot> {
+    let entries = match std::fs::read_dir(tenants_dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            tracing::error!("Failed to read tenants directory {}: {}", tenants_dir.display(), e);
+            return None;
+        }
+    };
+
+    let mut configs = HashMap::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+            continue;
+        }
+        let Some(name) = path.file_stem().and_then(|s| s.to_str()).map(str::to_string) else {
+            continue;
+        };
+        if let Err(reason) = check_tenant_id(&name, &options.reserved_subdomains) {
+            tracing::error!("Rejected tenant config {}: {}", path.display(), reason);
+            continue;
+        }
+
+        match TenantConfig::load_from_file(path.clone()) {
+            Ok(config) => {
+                configs.insert(name, config);
+            }
+            Err(e) => {
+                tracing::error!("Rejected tenant config {}: {}", path.display(), e);
+                if let Some(last_good) = previous.configs.get(&name) {
+                    configs.insert(name, last_good.clone());
+                }
+            }
+        }
+    }
+
+    let custom_domains = build_custom_domains(&configs, &options.base_domains);
+    Some(Snapshot { configs, custom_domains })
+}
+
+/// Name, modification time and size of each file in `tenants_dir`
+fn fingerprint(tenants_dir: &Path) -> DirFingerprint {
+    let Ok(entries) = std::fs::read_dir(tenants_dir) else {
+        return Vec::new();
+    };
+    let mut fingerprint: DirFingerprint = entries
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            // fs::metadata follows symlinks, so ConfigMap-style `..data`
+            // symlink swaps show up as a change to the linked file
+            let metadata = std::fs::metadata(&path).ok()?;
+            let name = path.file_name()?.to_str()?.to_string();
+            Some((name, metadata.modified().ok(), metadata.len()))
+        })
+        .collect();
+    fingerprint.sort();
+    fingerprint
+}
+
+/// Map each tenant's custom domains (and their "www." variants, when the
+/// tenant redirects them) to a mode. Tenants are visited in name order so a
+/// host claimed twice consistently goes to the same one. Domains under a base
+/// domain are skipped.
+fn build_custom_domains(configs: &HashMap<String, TenantConfig>, base_domains: &[String]) -> HashMap<String, SubdomainMode> {
+    let mut names: Vec<&String> = configs.keys().collect();
+    names.sort();
+
+    let mut domains = HashMap::new();
+    for name in names {
+        let config = &configs[name];
+        for domain in &config.custom_domains {
+            let domain = domain.trim().trim_end_matches('.').to_ascii_lowercase();
+            if let Err(reason) = check_custom_domain(&domain, base_domains) {
+                tracing::error!("Rejected custom domain {} for {}: {}", domain, name, reason);
+                continue;
+            }
+            let mut claims = vec![(domain.clone(), SubdomainMode::EnterpriseTenant(name.clone()))];
+            if config.redirect_www {
+                claims.push((format!("www.{}", domain), SubdomainMode::WwwRedirect));
+            }
+            for (host, mode) in claims {
+                if domains.contains_key(&host) {
+                    tracing::error!("Custom domain {} claimed by more than one tenant; ignoring it for {}", host, name);
+                    continue;
+                }
+                domains.insert(host, mode);
+            }
+        }
+    }
+    domains
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::test_support::temp_dir;
+
+    fn write_tenant(dir: &std::path::Path, name: &str, display_name: &str) {
+        let json = format!(r#"{{"name": "{}", "default_remote": null, "allowed_remotes": null}}"#, display_name);
+        std::fs::write(dir.join(format!("{}.json", name)), json).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_reload_picks_up_changes() {
+        let tmp = temp_dir();
+        let dir = tmp.path().to_path_buf();
+        write_tenant(&dir, "acme", "Acme");
+        let manager = TenantManager::new(dir.clone());
+        assert_eq!(manager.get_config("acme").await.name, "Acme");
+
+        write_tenant(&dir, "acme", "Acme Corp");
+        manager.reload().await;
+        assert_eq!(manager.get_config("acme").await.name, "Acme Corp");
+    }
+
+    #[tokio::test]
+    async fn test_check_ready() {
+        let tmp = temp_dir();
+        let dir = tmp.path().to_path_buf();
+        let manager = TenantManager::new(dir.clone());
+        assert!(manager.check_ready().await.is_ok(), "built-in default is enough");
+
+        std::fs::write(dir.join("default.json"), "{not json").unwrap();
+        assert_eq!(manager.check_ready().await, Err("default_tenant_invalid"));
+
+        write_tenant(&dir, "default", "Default");
+        assert!(manager.check_ready().await.is_ok());
+
+        let missing = TenantManager::new(dir.join("missing"));
+        assert_eq!(missing.check_ready().await, Err("tenants_dir_unreadable"));
+    }
+
+    #[tokio::test]
+    async fn test_reload_keeps_last_good_config_for_invalid_file() {
+        let tmp = temp_dir();
+        let dir = tmp.path().to_path_buf();
+        write_tenant(&dir, "acme", "Acme");
+        let manager = TenantManager::new(dir.clone());
+        manager.reload().await;
+
+        std::fs::write(dir.join("acme.json"), "{ not json").unwrap();
+        manager.reload().await;
+        assert_eq!(manager.get_config("acme").await.name, "Acme");
+    }
+
+    #[tokio::test]
+    async fn test_reload_drops_cached_default_for_new_tenant() {
+        let tmp = temp_dir();
+        let dir = tmp.path().to_path_buf();
+        let manager = TenantManager::new(dir.clone());
+        assert_eq!(manager.get_config("acme").await.name, "default");
+
+        write_tenant(&dir, "acme", "Acme");
+        manager.reload().await;
+        assert_eq!(manager.get_config("acme").await.name, "Acme");
+    }
+
+    #[tokio::test]
+    async fn test_unknown_tenant_is_negatively_cached() {
+        let tmp = temp_dir();
+        let dir = tmp.path().to_path_buf();
+        let manager = TenantManager::new(dir.clone());
+        assert!(manager.lookup("acme").await.is_none());
+
+        // Within the TTL the miss is served from cache, not the disk
+        write_tenant(&dir, "acme", "Acme");
+        assert!(manager.lookup("acme").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_negative_entry_expires() {
+        let tmp = temp_dir();
+        let dir = tmp.path().to_path_buf();
+        let options = TenantOptions { negative_ttl: Duration::ZERO, ..TenantOptions::default() };
+        let manager = TenantManager::with_options(dir.clone(), options);
+        assert!(manager.lookup("acme").await.is_none());
+
+        write_tenant(&dir, "acme", "Acme");
+        assert_eq!(manager.lookup("acme").await.unwrap().name, "Acme");
+    }
+
+    #[tokio::test]
+    async fn test_negative_cache_is_bounded() {
+        let tmp = temp_dir();
+        let dir = tmp.path().to_path_buf();
+        let options = TenantOptions { negative_cache_size: 2, ..TenantOptions::default() };
+        let manager = TenantManager::with_options(dir, options);
+        for name in ["a", "b", "c", "d"] {
+            manager.lookup(name).await;
+        }
+        assert_eq!(manager.unknown.lock().unwrap().len(), 2);
+        assert_eq!(manager.loaded_count().await, 0);
+    }
+
+    fn bases() -> Vec<String> {
+        vec!["srcuri.com".to_string()]
+    }
+
+    #[tokio::test]
+    async fn test_custom_domain_maps_to_tenant() {
+        let tmp = temp_dir();
+        let dir = tmp.path().to_path_buf();
+        std::fs::write(
+            dir.join("acme.json"),
+            r#"{"name": "Acme", "custom_domains": ["Code.Acme.com"]}"#,
+        )
+        .unwrap();
+        let manager = TenantManager::new(dir);
+        manager.reload().await;
+
+        let uri = Uri::from_static("/");
+        assert_eq!(
+            manager.detect_mode("code.acme.com:443", &uri, &bases()),
+            SubdomainMode::EnterpriseTenant("acme".to_string())
+        );
+        assert_eq!(manager.detect_mode("www.code.acme.com", &uri, &bases()), SubdomainMode::WwwRedirect);
+        assert_eq!(manager.detect_mode("other.acme.com", &uri, &bases()), SubdomainMode::DirectProtocol);
+        assert_eq!(
+            manager.resolve("code.acme.com", &uri, &bases()).await.config().unwrap().name,
+            "Acme"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_custom_domain_www_redirect_can_be_disabled() {
+        let tmp = temp_dir();
+        let dir = tmp.path().to_path_buf();
+        std::fs::write(
+            dir.join("acme.json"),
+            r#"{"name": "Acme", "custom_domains": ["code.acme.com"], "redirect_www": false}"#,
+        )
+        .unwrap();
+        let manager = TenantManager::new(dir);
+        manager.reload().await;
+
+        assert_eq!(
+            manager.detect_mode("www.code.acme.com", &Uri::from_static("/"), &bases()),
+            SubdomainMode::DirectProtocol
+        );
+    }
+
+    #[tokio::test]
+    async fn test_custom_domain_conflict_goes_to_first_tenant() {
+        let tmp = temp_dir();
+        let dir = tmp.path().to_path_buf();
+        std::fs::write(dir.join("acme.json"), r#"{"name": "Acme", "custom_domains": ["code.example.com"]}"#).unwrap();
+        std::fs::write(dir.join("zeta.json"), r#"{"name": "Zeta", "custom_domains": ["code.example.com"]}"#).unwrap();
+        let manager = TenantManager::new(dir);
+        manager.reload().await;
+
+        assert_eq!(
+            manager.detect_mode("code.example.com", &Uri::from_static("/"), &bases()),
+            SubdomainMode::EnterpriseTenant("acme".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_custom_domain_under_base_domain_is_rejected() {
+        let tmp = temp_dir();
+        let dir = tmp.path().to_path_buf();
+        std::fs::write(
+            dir.join("acme.json"),
+            r#"{"name": "Acme", "custom_domains": ["srcuri.com", "API.srcuri.com", "code.acme.com"]}"#,
+        )
+        .unwrap();
+        let manager = TenantManager::new(dir);
+        manager.reload().await;
+
+        let uri = Uri::from_static("/");
+        assert_eq!(manager.detect_mode("srcuri.com", &uri, &bases()), SubdomainMode::DirectProtocol);
+        assert_eq!(manager.detect_mode("www.srcuri.com", &uri, &bases()), SubdomainMode::WwwRedirect);
+        assert_eq!(
+            manager.detect_mode("api.srcuri.com", &uri, &bases()),
+            SubdomainMode::Reserved("api".to_string())
+        );
+        assert_eq!(
+            manager.detect_mode("code.acme.com", &uri, &bases()),
+            SubdomainMode::EnterpriseTenant("acme".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reserved_subdomain_mode() {
+        let tmp = temp_dir();
+        let manager = TenantManager::new(tmp.path().to_path_buf());
+        assert_eq!(
+            manager.detect_mode("api.srcuri.com", &Uri::from_static("/"), &bases()),
+            SubdomainMode::Reserved("api".to_string())
+        );
+        assert!(manager
+            .resolve("api.srcuri.com", &Uri::from_static("/"), &bases())
+            .await
+            .config
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_reserved_and_invalid_tenant_files_are_rejected() {
+        let tmp = temp_dir();
+        let dir = tmp.path().to_path_buf();
+        write_tenant(&dir, "api", "Shadow API");
+        write_tenant(&dir, "Bad_Name", "Bad");
+        write_tenant(&dir, "acme", "Acme");
+        let manager = TenantManager::new(dir);
+        manager.reload().await;
+
+        let snapshot = manager.snapshot();
+        assert_eq!(snapshot.configs.len(), 1);
+        assert!(snapshot.configs.contains_key("acme"));
+    }
+
+    #[tokio::test]
+    async fn test_reserved_list_is_configurable() {
+        let options = TenantOptions { reserved_subdomains: vec!["internal".to_string()], ..TenantOptions::default() };
+        let tmp = temp_dir();
+        let manager = TenantManager::with_options(tmp.path().to_path_buf(), options);
+        let uri = Uri::from_static("/");
+        assert_eq!(
+            manager.detect_mode("internal.srcuri.com", &uri, &bases()),
+            SubdomainMode::Reserved("internal".to_string())
+        );
+        assert_eq!(
+            manager.detect_mode("api.srcuri.com", &uri, &bases()),
+            SubdomainMode::EnterpriseTenant("api".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_lookup_ignores_invalid_labels() {
+        let tmp = temp_dir();
+        let manager = TenantManager::new(tmp.path().to_path_buf());
+        assert!(manager.lookup("../etc/passwd").await.is_none());
+        assert_eq!(manager.unknown.lock().unwrap().len(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_lookup_ignores_reserved_names() {
+        let tmp = temp_dir();
+        let dir = tmp.path().to_path_buf();
+        let manager = TenantManager::new(dir.clone());
+        write_tenant(&dir, "www", "Shadow");
+        assert!(manager.lookup("www").await.is_none());
+        assert_eq!(manager.loaded_count().await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_fingerprint_changes_when_file_added() {
+        let tmp = temp_dir();
+        let dir = tmp.path().to_path_buf();
+        let manager = TenantManager::new(dir.clone());
+        let before = manager.fingerprint().await;
+        write_tenant(&dir, "acme", "Acme");
+        assert_ne!(before, manager.fingerprint().await);
+    }
+
+    #[tokio::test]
+    async fn test_lazy_load_keeps_custom_domains() {
+        let tmp = temp_dir();
+        let dir = tmp.path().to_path_buf();
+        std::fs::write(dir.join("acme.json"), r#"{"name": "Acme", "custom_domains": ["code.acme.com"]}"#).unwrap();
+        let manager = TenantManager::new(dir.clone());
+        manager.reload().await;
+
+        write_tenant(&dir, "zeta", "Zeta");
+        assert_eq!(manager.lookup("zeta").await.unwrap().name, "Zeta");
+        assert!(manager.is_known_host("code.acme.com", &bases()));
+        assert_eq!(manager.loaded_count().await, 2);
+    }
+}
@@ -0,0 +1,79 @@
+// Per-request tenant resolution
+// The tenant a request is addressed to is resolved once, by
+// `resolve_tenant_middleware`, and stored as a request extension. The access
+// log, rate limiter, tenant guard and handlers all read that extension rather
+// than detecting the mode and looking the tenant up again.
+
+use axum::{
+    async_trait,
+    body::Body,
+    extract::{FromRequestParts, Request, State},
+    http::{header, request::Parts},
+    middleware::Next,
+    response::Response,
+};
+use std::convert::Infallible;
+use std::sync::Arc;
+
+use super::config::TenantConfig;
+use crate::subdomain::SubdomainMode;
+use crate::AppState;
+
+/// The mode and tenant config for one request
+#[derive(Debug, Clone)]
+pub struct ResolvedTenant {
+    pub mode: SubdomainMode,
+    /// Config of an `EnterpriseTenant` with a tenant file; `None` on the
+    /// public site and for unknown tenants
+    pub config: Option<Arc<TenantConfig>>,
+    /// The request's hostname, when it is one the server answers for: a base
+    /// domain, a subdomain of one, or a tenant's custom domain
+    pub host: Option<String>,
+}
+
+impl ResolvedTenant {
+    /// The public site, for requests that never went through the middleware
+    pub fn public() -> Self {
+        Self { mode: SubdomainMode::DirectProtocol, config: None, host: None }
+    }
+
+    /// Tenant ID for rate-limit buckets and logs. Unknown tenants share
+    /// "default" so made-up hostnames can't mint fresh budgets.
+    pub fn id(&self) -> &str {
+        match (&self.mode, &self.config) {
+            (SubdomainMode::EnterpriseTenant(name), Some(_)) => name,
+            _ => "default",
+        }
+    }
+
+    pub fn config(&self) -> Option<&TenantConfig> {
+        self.config.as_deref()
+    }
+
+    /// Host for absolute links back to this site. Workspace mirror links only
+    /// resolve on the tenant's own host, so stay on it when there is one.
+    pub fn link_host<'a>(&'a self, fallback: &'a str) -> &'a str {
+        self.host.as_deref().unwrap_or(fallback)
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for ResolvedTenant {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(parts.extensions.get::<ResolvedTenant>().cloned().unwrap_or_else(ResolvedTenant::public))
+    }
+}
+
+/// Resolve the request's tenant from its Host and store it as a `ResolvedTenant`
+pub async fn resolve_tenant_middleware(State(state): State<AppState>, mut request: Request<Body>, next: Next) -> Response {
+    let host = request
+        .headers()
+        .get(header::HOST)
+        .and_then(|h| h.to_str().ok())
+        .unwrap_or(state.primary_domain());
+    let resolved = state.tenant_manager.resolve(host, request.uri(), &state.base_domains).await;
+    request.extensions_mut().insert(resolved);
+    next.run(request).await
+}
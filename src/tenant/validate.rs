@@ -0,0 +1,325 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use super::config::TenantConfig;
+use super::TenantOptions;
+
+/// A problem found in a tenant file, with its position when known
+#[derive(Debug, Clone, PartialEq)]
+pub struct Problem {
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+/// Result of validating one tenant file
+#[derive(Debug)]
+pub struct FileReport {
+    pub path: PathBuf,
+    pub config: Option<TenantConfig>,
+    pub problems: Vec<Problem>,
+}
+
+impl FileReport {
+    pub fn is_valid(&self) -> bool {
+        self.config.is_some() && self.problems.is_empty()
+    }
+}
+
+impl fmt::Display for FileReport {
+    /// One `path:line:column: message` line per problem, compiler style
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for problem in &self.problems {
+            write!(f, "{}", self.path.display())?;
+            if let Some(line) = problem.line {
+                write!(f, ":{}", line)?;
+                if let Some(column) = problem.column {
+                    write!(f, ":{}", column)?;
+                }
+            }
+            writeln!(f, ": {}", problem.message)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse a tenant file strictly: syntax and schema errors carry their line
+/// and column, and keys `TenantConfig` doesn't know about are reported
+/// instead of being silently ignored. `options` are the server's, so a file
+/// passes exactly when the running server would accept it.
+pub fn validate_file(path: &Path, options: &TenantOptions) -> FileReport {
+    let mut report = FileReport {
+        path: path.to_path_buf(),
+        config: None,
+        problems: Vec::new(),
+    };
+
+    if let Some(name) = path.file_stem().and_then(|s| s.to_str()) {
+        if let Err(message) = super::check_tenant_id(name, &options.reserved_subdomains) {
+            report.problems.push(Problem { line: None, column: None, message });
+        }
+    }
+
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) => {
+            report.problems.push(Problem { line: None, column: None, message: e.to_string() });
+            return report;
+        }
+    };
+
+    let mut unknown_keys = Vec::new();
+    let mut deserializer = serde_json::Deserializer::from_str(&content);
+    let parsed = serde_ignored::deserialize(&mut deserializer, |path| unknown_keys.push(path.to_string()))
+        .and_then(|config: TenantConfig| deserializer.end().map(|_| config));
+
+    match parsed {
+        Ok(config) => {
+            for key in unknown_keys {
+                let leaf = key.rsplit('.').next().unwrap_or(&key);
+                report.problems.push(Problem {
+                    line: find_key_line(&content, leaf),
+                    column: None,
+                    message: format!("unknown key \"{}\"", key),
+                });
+            }
+            report.problems.extend(check_values(&config, options));
+            report.config = Some(config);
+        }
+        Err(e) => report.problems.push(Problem {
+            line: Some(e.line()),
+            column: Some(e.column()),
+            message: e.to_string(),
+        }),
+    }
+
+    report
+}
+
+/// Validate every `*.json` file in `dir`, sorted by file name. A custom
+/// domain already claimed by an earlier file is a problem in the later one,
+/// since the server ignores it there.
+pub fn validate_dir(dir: &Path, options: &TenantOptions) -> std::io::Result<Vec<FileReport>> {
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().and_then(|ext| ext.to_str()) == Some("json"))
+        .collect();
+    paths.sort();
+    let mut reports: Vec<FileReport> = paths.iter().map(|path| validate_file(path, options)).collect();
+
+    let mut claimed: HashMap<String, PathBuf> = HashMap::new();
+    for report in &mut reports {
+        let Some(config) = &report.config else { continue };
+        for domain in &config.custom_domains {
+            let domain = domain.trim().trim_end_matches('.').to_ascii_lowercase();
+            let mut hosts = vec![domain.clone()];
+            if config.redirect_www {
+                hosts.push(format!("www.{}", domain));
+            }
+            for host in hosts {
+                match claimed.get(&host) {
+                    Some(owner) => report.problems.push(Problem {
+                        line: None,
+                        column: None,
+                        message: format!("custom domain \"{}\" is already claimed by {}", host, owner.display()),
+                    }),
+                    None => {
+                        claimed.insert(host, report.path.clone());
+                    }
+                }
+            }
+        }
+    }
+    Ok(reports)
+}
+
+/// Semantic checks on a config that parsed successfully
+fn check_values(config: &TenantConfig, options: &TenantOptions) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    let mut problem = |message: String| problems.push(Problem { line: None, column: None, message });
+
+    if config.name.trim().is_empty() {
+        problem("\"name\" must not be empty".to_string());
+    }
+    if let Some(ref patterns) = config.allowed_remotes {
+        for pattern in patterns.iter().filter(|p| p.trim().is_empty()) {
+            problem(format!("\"allowed_remotes\" contains an empty entry {:?}", pattern));
+        }
+    }
+    if let Some(Err(message)) = config.rate_limit.as_ref().map(|policy| policy.validate()) {
+        problem(format!("\"rate_limit\": {}", message));
+    }
+    for domain in &config.custom_domains {
+        let valid = !domain.is_empty()
+            && domain.contains('.')
+            && domain.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '.'));
+        if !valid {
+            problem(format!("custom domain {:?} must be a bare hostname like \"code.example.com\"", domain));
+        } else if let Err(message) = super::check_custom_domain(domain, &options.base_domains) {
+            problem(message);
+        }
+    }
+    for (name, workspace) in &config.workspaces {
+        if crate::routes::RESERVED_PATHS.contains(&name.as_str()) {
+            problem(format!("workspace \"{}\" is a reserved path; /{} is served by the server itself", name, name));
+        }
+        if workspace.remote.trim().is_empty() {
+            problem(format!("workspace \"{}\" has an empty \"remote\"", name));
+        }
+    }
+    problems
+}
+
+/// Line number (1-based) of the first occurrence of `"key":`
+fn find_key_line(content: &str, key: &str) -> Option<usize> {
+    let needle = format!("\"{}\"", key);
+    content
+        .lines()
+        .position(|line| {
+            line.find(&needle)
+                .is_some_and(|idx| line[idx + needle.len()..].trim_start().starts_with(':'))
+        })
+        .map(|idx| idx + 1)
+}
+
+/// Starter config written by `tenants new`
+pub fn template_config(name: &str) -> TenantConfig {
+    TenantConfig {
+        name: name.to_string(),
+        ..TenantConfig::default_config()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::test_support::{temp_dir, write_file};
+    use tempfile::TempDir;
+
+    /// Write `content` as acme.json in a fresh directory, kept alive by the returned guard
+    fn write_acme(content: &str) -> (TempDir, PathBuf) {
+        let dir = temp_dir();
+        let path = write_file(dir.path(), "acme.json", content);
+        (dir, path)
+    }
+
+    #[test]
+    fn test_valid_file() {
+        let (_dir, path) = write_acme(r#"{"name": "Acme", "default_remote": null, "allowed_remotes": ["github.com/acme/*"]}"#);
+        let report = validate_file(&path, &TenantOptions::default());
+        assert!(report.is_valid(), "{}", report);
+    }
+
+    #[test]
+    fn test_syntax_error_has_line_and_column() {
+        let (_dir, path) = write_acme("{\n  \"name\": \"Acme\",\n  \"default_remote\" null\n}");
+        let report = validate_file(&path, &TenantOptions::default());
+        assert!(!report.is_valid());
+        assert_eq!(report.problems[0].line, Some(3));
+        assert!(report.problems[0].column.is_some());
+    }
+
+    #[test]
+    fn test_missing_field_is_reported() {
+        let (_dir, path) = write_acme(r#"{"default_remote": null}"#);
+        let report = validate_file(&path, &TenantOptions::default());
+        assert!(!report.is_valid());
+        assert!(report.problems[0].message.contains("name"));
+    }
+
+    #[test]
+    fn test_unknown_key_is_flagged_with_line() {
+        let (_dir, path) = write_acme(
+            "{\n  \"name\": \"Acme\",\n  \"default_remote\": null,\n  \"allowed_remote\": [\"github.com\"]\n}",
+        );
+        let report = validate_file(&path, &TenantOptions::default());
+        assert!(!report.is_valid());
+        assert_eq!(report.problems.len(), 1);
+        assert_eq!(report.problems[0].line, Some(4));
+        assert!(report.problems[0].message.contains("allowed_remote"));
+    }
+
+    #[test]
+    fn test_unknown_nested_key_is_flagged() {
+        let (_dir, path) = write_acme(
+            r#"{"name": "Acme", "default_remote": null, "workspaces": {"billing": {"remote": "github.com/acme/billing", "defualt_branch": "main"}}}"#,
+        );
+        let report = validate_file(&path, &TenantOptions::default());
+        assert!(report.problems[0].message.contains("workspaces.billing.defualt_branch"));
+    }
+
+    #[test]
+    fn test_reserved_or_invalid_file_name_is_flagged() {
+        let (_dir, path) = write_acme(r#"{"name": "API", "default_remote": null}"#);
+        let reserved = path.with_file_name("api.json");
+        std::fs::rename(&path, &reserved).unwrap();
+        let report = validate_file(&reserved, &TenantOptions::default());
+        assert!(!report.is_valid());
+        assert!(report.problems[0].message.contains("reserved"));
+
+        let invalid = reserved.with_file_name("Acme_Corp.json");
+        std::fs::rename(&reserved, &invalid).unwrap();
+        let report = validate_file(&invalid, &TenantOptions::default());
+        assert!(report.problems[0].message.contains("DNS label"));
+    }
+
+    #[test]
+    fn test_configured_reserved_list_is_used() {
+        let (_dir, path) = write_acme(r#"{"name": "Internal", "default_remote": null}"#);
+        let internal = path.with_file_name("internal.json");
+        std::fs::rename(&path, &internal).unwrap();
+        let options = TenantOptions { reserved_subdomains: vec!["internal".to_string()], ..TenantOptions::default() };
+        assert!(!validate_file(&internal, &options).is_valid());
+
+        // "api" is only reserved by default; the server configured above would serve it
+        let api = internal.with_file_name("api.json");
+        std::fs::rename(&internal, &api).unwrap();
+        assert!(validate_file(&api, &options).is_valid());
+    }
+
+    #[test]
+    fn test_custom_domain_under_base_domain_is_flagged() {
+        for domain in ["srcuri.com", "api.srcuri.com", "Acme.SrcUri.com"] {
+            let content = format!(r#"{{"name": "Acme", "default_remote": null, "custom_domains": ["{}"]}}"#, domain);
+            let (_dir, path) = write_acme(&content);
+            let report = validate_file(&path, &TenantOptions::default());
+            assert!(!report.is_valid(), "{} should be rejected", domain);
+            assert!(report.problems[0].message.contains("base domain"));
+        }
+        let content = r#"{"name": "Acme", "default_remote": null, "custom_domains": ["code.acme.com"]}"#;
+        let (_dir, path) = write_acme(content);
+        assert!(validate_file(&path, &TenantOptions::default()).is_valid());
+    }
+
+    #[test]
+    fn test_custom_domain_claimed_twice_is_flagged() {
+        let (_dir, path) = write_acme(r#"{"name": "Acme", "custom_domains": ["code.example.com"]}"#);
+        let dir = path.parent().unwrap();
+        std::fs::write(dir.join("zeta.json"), r#"{"name": "Zeta", "custom_domains": ["Code.Example.com"]}"#).unwrap();
+
+        let reports = validate_dir(dir, &TenantOptions::default()).unwrap();
+        assert!(reports[0].is_valid(), "{}", reports[0]);
+        assert!(!reports[1].is_valid());
+        assert!(reports[1].problems[0].message.contains("already claimed by"));
+    }
+
+    #[test]
+    fn test_workspace_named_after_a_route_is_flagged() {
+        let (_dir, path) = write_acme(
+            r#"{"name": "Acme", "workspaces": {"metrics": {"remote": "github.com/acme/metrics"}}}"#,
+        );
+        let report = validate_file(&path, &TenantOptions::default());
+        assert!(!report.is_valid());
+        assert!(report.problems[0].message.contains("reserved path"));
+    }
+
+    #[test]
+    fn test_display_is_compiler_style() {
+        let (_dir, path) = write_acme("{\n  \"name\": \"\",\n  \"default_remote\": null\n}");
+        let output = validate_file(&path, &TenantOptions::default()).to_string();
+        assert!(output.starts_with(&path.display().to_string()));
+        assert!(output.contains("\"name\" must not be empty"));
+    }
+}
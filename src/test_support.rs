@@ -0,0 +1,17 @@
+// Shared unit-test helpers
+// Declared by both lib.rs and main.rs, so the binary's tests can use them too.
+
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+/// A fresh directory, unique to the caller and removed when dropped
+pub fn temp_dir() -> TempDir {
+    tempfile::Builder::new().prefix("sorcery-").tempdir().unwrap()
+}
+
+/// Write `content` to `name` inside `dir`, returning its path
+pub fn write_file(dir: &Path, name: &str, content: &str) -> PathBuf {
+    let path = dir.join(name);
+    std::fs::write(&path, content).unwrap();
+    path
+}
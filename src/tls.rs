@@ -0,0 +1,302 @@
+// TLS termination
+// Optional HTTPS listeners for installs without a reverse proxy. The default
+// certificate covers the base domains (usually a wildcard); tenant custom
+// domains get their own certificate by SNI from `<hostname>.crt` and
+// `<hostname>.key` in `certs_dir`. Files are re-read when they change, so
+// rotated certificates are picked up without a restart.
+
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, StatusCode, Uri},
+    response::{IntoResponse, Redirect, Response},
+    Router,
+};
+use rustls::crypto::CryptoProvider;
+use rustls::server::{ClientHello, ResolvesServerCert};
+use rustls::sign::CertifiedKey;
+use rustls_pki_types::pem::PemObject;
+use rustls_pki_types::{CertificateDer, PrivateKeyDer};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+
+use crate::config::TlsConfig;
+use crate::subdomain;
+use crate::AppState;
+
+#[derive(Default)]
+struct Certs {
+    default: Option<Arc<CertifiedKey>>,
+    /// Lowercase hostname to certificate, from `certs_dir`
+    by_host: HashMap<String, Arc<CertifiedKey>>,
+}
+
+/// Certificates for the HTTPS listeners, chosen per connection by SNI
+pub struct CertStore {
+    cert: PathBuf,
+    key: PathBuf,
+    certs_dir: Option<PathBuf>,
+    certs: RwLock<Arc<Certs>>,
+}
+
+impl std::fmt::Debug for CertStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CertStore").field("cert", &self.cert).field("certs_dir", &self.certs_dir).finish()
+    }
+}
+
+/// File names and modification times of every certificate file, used to detect rotation
+type CertFingerprint = Vec<(PathBuf, Option<SystemTime>, u64)>;
+
+impl CertStore {
+    /// Load every certificate. Unlike `reload`, any unreadable or mismatched
+    /// certificate is an error, so a bad install fails at startup.
+    pub fn load(config: &TlsConfig) -> Result<Self, String> {
+        let (Some(cert), Some(key)) = (&config.cert, &config.key) else {
+            return Err("tls.cert and tls.key are required".to_string());
+        };
+        let store = Self {
+            cert: cert.clone(),
+            key: key.clone(),
+            certs_dir: config.certs_dir.clone(),
+            certs: RwLock::new(Arc::new(Certs::default())),
+        };
+        let (certs, errors) = store.read_certs(&Certs::default());
+        if !errors.is_empty() {
+            return Err(errors.join("; "));
+        }
+        *store.certs.write().unwrap() = Arc::new(certs);
+        Ok(store)
+    }
+
+    /// Re-read the certificate files. A certificate that fails to load keeps
+    /// its last good version, so a half-written rotation never takes a host offline.
+    pub fn reload(&self) {
+        let previous = self.certs.read().unwrap().clone();
+        let (certs, errors) = self.read_certs(&previous);
+        for error in &errors {
+            tracing::error!("Rejected certificate: {}", error);
+        }
+        tracing::info!("Loaded TLS certificates for {} custom domains", certs.by_host.len());
+        *self.certs.write().unwrap() = Arc::new(certs);
+    }
+
+    /// Poll the certificate files and reload whenever one is added, removed or modified
+    pub fn watch(self: Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut last = self.fingerprint();
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+            loop {
+                ticker.tick().await;
+                let current = self.fingerprint();
+                if current != last {
+                    tracing::info!("TLS certificates changed on disk, reloading");
+                    self.reload();
+                    last = current;
+                }
+            }
+        })
+    }
+
+    /// Hostnames with their own certificate in `certs_dir`, sorted
+    pub fn custom_hosts(&self) -> Vec<String> {
+        let mut hosts: Vec<String> = self.certs.read().unwrap().by_host.keys().cloned().collect();
+        hosts.sort();
+        hosts
+    }
+
+    /// rustls config that resolves certificates from this store, for HTTP/2 and HTTP/1.1
+    pub fn server_config(self: Arc<Self>) -> Arc<rustls::ServerConfig> {
+        let mut config = rustls::ServerConfig::builder_with_provider(Arc::new(provider()))
+            .with_safe_default_protocol_versions()
+            .expect("ring supports the default protocol versions")
+            .with_no_client_auth()
+            .with_cert_resolver(self);
+        config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+        Arc::new(config)
+    }
+
+    fn read_certs(&self, previous: &Certs) -> (Certs, Vec<String>) {
+        let mut errors = Vec::new();
+        let default = match load_certified_key(&self.cert, &self.key) {
+            Ok(key) => Some(Arc::new(key)),
+            Err(e) => {
+                errors.push(e);
+                previous.default.clone()
+            }
+        };
+
+        let mut by_host = HashMap::new();
+        for (host, cert, key) in self.custom_cert_files() {
+            match load_certified_key(&cert, &key) {
+                Ok(certified) => {
+                    by_host.insert(host, Arc::new(certified));
+                }
+                Err(e) => {
+                    errors.push(e);
+                    if let Some(last_good) = previous.by_host.get(&host) {
+                        by_host.insert(host, last_good.clone());
+                    }
+                }
+            }
+        }
+        (Certs { default, by_host }, errors)
+    }
+
+    /// `(hostname, cert path, key path)` for each `<hostname>.crt` in `certs_dir`
+    fn custom_cert_files(&self) -> Vec<(String, PathBuf, PathBuf)> {
+        let Some(dir) = &self.certs_dir else {
+            return Vec::new();
+        };
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            tracing::error!("Failed to read TLS certs directory {}", dir.display());
+            return Vec::new();
+        };
+        entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "crt"))
+            .filter_map(|path| {
+                let host = path.file_stem()?.to_str()?.to_ascii_lowercase();
+                if !host.split('.').all(subdomain::is_valid_dns_label) {
+                    tracing::error!("Ignoring {}: not named after a hostname", path.display());
+                    return None;
+                }
+                let key = path.with_extension("key");
+                Some((host, path, key))
+            })
+            .collect()
+    }
+
+    fn fingerprint(&self) -> CertFingerprint {
+        let mut paths = vec![self.cert.clone(), self.key.clone()];
+        if let Some(Ok(entries)) = self.certs_dir.as_ref().map(std::fs::read_dir) {
+            paths.extend(entries.flatten().map(|entry| entry.path()));
+        }
+        let mut fingerprint: CertFingerprint = paths
+            .into_iter()
+            .map(|path| {
+                // Follows symlinks, so Kubernetes secret swaps count as changes
+                let metadata = std::fs::metadata(&path).ok();
+                let modified = metadata.as_ref().and_then(|m| m.modified().ok());
+                let len = metadata.map_or(0, |m| m.len());
+                (path, modified, len)
+            })
+            .collect();
+        fingerprint.sort();
+        fingerprint
+    }
+}
+
+impl ResolvesServerCert for CertStore {
+    fn resolve(&self, client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        let certs = self.certs.read().unwrap();
+        client_hello
+            .server_name()
+            .and_then(|name| certs.by_host.get(&name.to_ascii_lowercase()))
+            .or(certs.default.as_ref())
+            .cloned()
+    }
+}
+
+fn provider() -> CryptoProvider {
+    rustls::crypto::ring::default_provider()
+}
+
+/// Certificate chain and matching private key from PEM files
+fn load_certified_key(cert_path: &Path, key_path: &Path) -> Result<CertifiedKey, String> {
+    let chain = CertificateDer::pem_file_iter(cert_path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| format!("{}: {}", cert_path.display(), e))?;
+    if chain.is_empty() {
+        return Err(format!("{}: no certificates found", cert_path.display()));
+    }
+    let key = PrivateKeyDer::from_pem_file(key_path).map_err(|e| format!("{}: {}", key_path.display(), e))?;
+    CertifiedKey::from_der(chain, key, &provider())
+        .map_err(|e| format!("{} and {}: {}", cert_path.display(), key_path.display(), e))
+}
+
+#[derive(Clone)]
+struct RedirectState {
+    app: AppState,
+    https_port: u16,
+}
+
+/// Router for plain-HTTP listeners that permanently redirects requests to the
+/// same host and path over HTTPS on `https_port`. Only hosts the server
+/// answers for are redirected, so it can't be used as an open redirect.
+pub fn redirect_router(app: AppState, https_port: u16) -> Router {
+    Router::new().fallback(redirect_to_https).with_state(RedirectState { app, https_port })
+}
+
+async fn redirect_to_https(State(state): State<RedirectState>, headers: HeaderMap, uri: Uri) -> Response {
+    let host = headers
+        .get(header::HOST)
+        .and_then(|h| h.to_str().ok())
+        .map(|h| subdomain::strip_port(h).trim_end_matches('.').to_ascii_lowercase())
+        .filter(|h| h.split('.').all(subdomain::is_valid_dns_label))
+        .filter(|h| state.app.tenant_manager.is_known_host(h, &state.app.base_domains));
+    let Some(host) = host else {
+        return (StatusCode::BAD_REQUEST, "Missing or unknown Host header").into_response();
+    };
+    let https_port = state.https_port;
+
+    let authority = if https_port == 443 { host } else { format!("{}:{}", host, https_port) };
+    let path = uri.path_and_query().map(|pq| pq.as_str()).unwrap_or("/");
+    Redirect::permanent(&format!("https://{}{}", authority, path)).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::Request;
+    use tower::ServiceExt;
+
+    async fn redirect(https_port: u16, host: &str, uri: &str) -> Response {
+        let dir = crate::test_support::temp_dir();
+        std::fs::write(dir.path().join("acme.json"), r#"{"name": "Acme", "custom_domains": ["links.acme.com"]}"#).unwrap();
+        let tenant_manager = crate::tenant::TenantManager::new(dir.path().to_path_buf());
+        tenant_manager.reload().await;
+        let app = AppState {
+            tenant_manager: Arc::new(tenant_manager),
+            base_domains: vec!["srcuri.com".to_string()],
+            shutdown: crate::shutdown::Shutdown::new(),
+        };
+        redirect_router(app, https_port)
+            .oneshot(Request::builder().uri(uri).header("host", host).body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_redirect_keeps_host_and_path() {
+        let response = redirect(443, "links.acme.com:80", "/repo/src/lib.rs:42?branch=main").await;
+        assert_eq!(response.status(), StatusCode::PERMANENT_REDIRECT);
+        assert_eq!(response.headers()["location"], "https://links.acme.com/repo/src/lib.rs:42?branch=main");
+    }
+
+    #[tokio::test]
+    async fn test_redirect_to_non_default_port() {
+        let response = redirect(8443, "srcuri.com", "/").await;
+        assert_eq!(response.headers()["location"], "https://srcuri.com:8443/");
+    }
+
+    #[tokio::test]
+    async fn test_redirect_rejects_bad_host() {
+        let response = redirect(443, "evil.com/@x", "/").await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_redirect_only_for_known_hosts() {
+        let response = redirect(443, "acme.srcuri.com", "/").await;
+        assert_eq!(response.headers()["location"], "https://acme.srcuri.com/");
+        for host in ["evil.com", "srcuri.com.evil.com", "other.acme.com"] {
+            let response = redirect(443, host, "/").await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", host);
+        }
+    }
+}
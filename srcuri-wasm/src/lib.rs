@@ -0,0 +1,92 @@
+//! Provider URL parsing for the browser.
+//!
+//! The provider and landing pages parse links client-side, since only the
+//! browser sees the `#L42` fragment. Compiled to WebAssembly, this crate gives
+//! them `srcuri_core::parse_remote_url` and the server's `srcuri://` link
+//! builder, so a parser fix reaches both without being written twice.
+
+use srcuri_core::{ParseError, SrcuriTarget};
+
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+/// A parsed provider link, as the page scripts see it
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen(getter_with_clone))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Link {
+    pub remote: String,
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(js_name = repoName))]
+    pub repo_name: String,
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(js_name = refValue))]
+    pub ref_value: Option<String>,
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(js_name = filePath))]
+    pub file_path: Option<String>,
+    pub line: Option<u32>,
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(js_name = srcuriUrl))]
+    pub srcuri_url: String,
+}
+
+/// Parse a provider URL as typed or taken from a page path: leading slashes
+/// are dropped and `https://` is assumed when there's no scheme
+pub fn parse_link(input: &str) -> Result<Link, ParseError> {
+    let trimmed = input.trim().trim_start_matches('/');
+    let url = if trimmed.starts_with("http://") || trimmed.starts_with("https://") {
+        trimmed.to_string()
+    } else {
+        format!("https://{}", trimmed)
+    };
+    let target = srcuri_core::parse_remote_url(&url)?;
+    Ok(Link {
+        srcuri_url: build_srcuri_url(&target),
+        remote: target.remote,
+        repo_name: target.repo_name,
+        ref_value: target.ref_value,
+        file_path: target.file_path,
+        line: target.line,
+    })
+}
+
+/// `parse_link` for JavaScript; throws an `Error` with the parser's message
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_name = parseRemoteUrl)]
+pub fn parse_remote_url_js(input: &str) -> Result<Link, JsError> {
+    parse_link(input).map_err(|e| JsError::new(&e.message))
+}
+
+/// Build the srcuri:// protocol URL for a target
+pub fn build_srcuri_url(target: &SrcuriTarget) -> String {
+    let mut srcuri = if target.is_absolute {
+        // Absolute path: srcuri:///path/to/file
+        let path = target.file_path.as_deref().unwrap_or("");
+        format!("srcuri://{}", path)
+    } else {
+        // Workspace path: srcuri://workspace/path/to/file
+        let mut s = format!("srcuri://{}/", target.repo_name);
+        if let Some(ref path) = target.file_path {
+            s.push_str(path);
+        }
+        s
+    };
+    if let Some(line) = target.line {
+        srcuri.push_str(&format!(":{}", line));
+    }
+
+    let mut query_parts = Vec::new();
+    if let Some(ref branch) = target.ref_value {
+        // URL-encode branch names to handle special characters like + # =
+        // Examples: "inputprocessing/c++" becomes "inputprocessing%2Fc%2B%2B"
+        //           "#pr470" becomes "%23pr470"
+        // Without encoding, + means space and # truncates at fragment delimiter.
+        let encoded: String = url::form_urlencoded::byte_serialize(branch.as_bytes()).collect();
+        query_parts.push(format!("branch={}", encoded));
+    }
+    if !target.remote.is_empty() {
+        // Always output with https:// prefix for git clone compatibility
+        query_parts.push(format!("remote=https://{}", target.remote));
+    }
+    if !query_parts.is_empty() {
+        srcuri.push('?');
+        srcuri.push_str(&query_parts.join("&"));
+    }
+    srcuri
+}
@@ -0,0 +1,97 @@
+// Runs the provider page's own script and srcuri-wasm against a live server,
+// for every entry in tests/fixtures/provider_urls.json.
+//
+//   node tests/browser/provider_page.mjs http://127.0.0.1:3000
+//
+// Each fixture is opened the way a browser would open srcuri.com/<link>: the
+// path and query go to the server, the fragment stays with the page. The
+// page's inline script then runs against a minimal DOM, with `location`
+// split into pathname, search and hash, and must navigate to the fixture's
+// srcuri:// URL, or show an error for fixtures that expect one.
+
+import { readFileSync } from 'node:fs';
+import vm from 'node:vm';
+
+const base = process.argv[2] || 'http://127.0.0.1:3000';
+const fixtures = JSON.parse(readFileSync(new URL('../fixtures/provider_urls.json', import.meta.url)));
+
+function element(attributes = {}) {
+    return {
+        style: {},
+        textContent: '',
+        href: '',
+        getAttribute: (name) => attributes[name] ?? null,
+        addEventListener() {},
+    };
+}
+
+/** Load the page at `pageUrl` and run its scripts; resolves to the outcome */
+async function runPage(pageUrl) {
+    const response = await fetch(new URL(pageUrl.pathname + pageUrl.search, base));
+    const html = await response.text();
+
+    const glue = html.match(/<script src="([^"]+)" integrity="[^"]+" id="srcuri-wasm" data-module="([^"]+)"><\/script>/);
+    if (!glue) {
+        throw new Error('page does not load srcuri-wasm; build the server with --features browser-wasm');
+    }
+    const inline = [...html.matchAll(/<script>([\s\S]*?)<\/script>/g)].map((match) => match[1]);
+
+    const elements = { 'srcuri-wasm': element({ 'data-module': glue[2] }) };
+    const outcome = {};
+    const location = {
+        pathname: pageUrl.pathname,
+        search: pageUrl.search,
+        hash: pageUrl.hash,
+        replace(url) { outcome.replaced = url; },
+        set href(url) { outcome.navigated = url; },
+    };
+    const window = {
+        location,
+        document: { getElementById: (id) => (elements[id] ??= element()) },
+        fetch: (url, init) => fetch(new URL(url, base), init),
+        WebAssembly,
+        Response,
+        URL,
+        TextEncoder,
+        TextDecoder,
+        console,
+    };
+    window.window = window;
+    const context = vm.createContext(window);
+
+    vm.runInContext(await (await fetch(new URL(glue[1], base))).text(), context);
+    for (const script of inline) {
+        vm.runInContext(script, context);
+    }
+
+    for (let waited = 0; waited < 5000; waited += 10) {
+        if (outcome.navigated || outcome.replaced || elements['error-container']?.style.display === 'block') {
+            break;
+        }
+        await new Promise((resolve) => setTimeout(resolve, 10));
+    }
+    outcome.error = elements['error-container']?.style.display === 'block' ? elements['error-message'].textContent : undefined;
+    return outcome;
+}
+
+const failures = [];
+for (const fixture of fixtures) {
+    const link = fixture.fragment ? `${fixture.url}#${fixture.fragment}` : fixture.url;
+    // Parsed the way the browser parses the address bar
+    const pageUrl = new URL('/' + link, base);
+    const outcome = await runPage(pageUrl);
+
+    if (fixture.expected) {
+        if (outcome.navigated !== fixture.expected.srcuri_url) {
+            failures.push(`${fixture.name}:\n  expected ${fixture.expected.srcuri_url}\n  got      ${JSON.stringify(outcome)}`);
+        }
+    } else if (outcome.error === undefined) {
+        failures.push(`${fixture.name}: expected an error, got ${JSON.stringify(outcome)}`);
+    }
+}
+
+if (failures.length > 0) {
+    console.error(`The provider page disagrees with provider_urls.json:\n${failures.join('\n')}`);
+    process.exit(1);
+}
+console.log(`${fixtures.length} fixtures passed through the provider page`);
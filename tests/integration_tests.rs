@@ -16,6 +16,95 @@ async fn test_health_endpoint() {
     assert_eq!(response.status(), StatusCode::OK);
 }
 
+#[tokio::test]
+async fn test_liveness_endpoint() {
+    let response = create_test_app()
+        .oneshot(Request::builder().uri("/health/live").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_readiness_endpoint() {
+    let response = create_test_app()
+        .oneshot(
+            Request::builder()
+                .uri("/health/ready")
+                .header("host", "admin.srcuri.com")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response_json(response).await["status"], "ready");
+}
+
+#[tokio::test]
+async fn test_readiness_fails_without_tenants_dir() {
+    let app = create_test_app_with_tenants(std::path::PathBuf::from("no-such-tenants-dir"));
+
+    let response = app
+        .oneshot(Request::builder().uri("/health/ready").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    let json = response_json(response).await;
+    assert_eq!(json["status"], "not_ready");
+    assert_eq!(json["reason"], "tenants_dir_unreadable");
+    assert!(!json.to_string().contains("no-such-tenants-dir"), "paths stay in the server log");
+}
+
+#[tokio::test]
+async fn test_readiness_fails_once_draining() {
+    use std::sync::Arc;
+
+    let shutdown = sorcery_server::shutdown::Shutdown::new();
+    let state = sorcery_server::AppState {
+        tenant_manager: Arc::new(sorcery_server::tenant::TenantManager::new(std::path::PathBuf::from("tenants"))),
+        base_domains: vec!["srcuri.com".to_string()],
+        shutdown: shutdown.clone(),
+    };
+    let app = test_router(state);
+
+    shutdown.start_draining();
+    let ready = app
+        .clone()
+        .oneshot(Request::builder().uri("/health/ready").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(ready.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(response_json(ready).await["reason"], "shutting_down");
+
+    // Draining instances still serve traffic until listeners stop
+    let live = app
+        .oneshot(Request::builder().uri("/.well-known/srcuri.json").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(live.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_version_endpoint() {
+    let response = create_test_app()
+        .oneshot(Request::builder().uri("/version").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = response_json(response).await;
+    assert_eq!(json["version"], env!("CARGO_PKG_VERSION"));
+    for field in ["git_sha", "build_time", "srcuri_core"] {
+        assert!(!json[field].as_str().unwrap().is_empty(), "{}", field);
+    }
+    assert!(json["build_time"].as_str().unwrap().ends_with('Z'));
+    assert!(json["tenants"].is_u64());
+}
+
 #[tokio::test]
 async fn test_wellknown_endpoint() {
     let app = create_test_app();
@@ -132,7 +221,8 @@ async fn test_passthrough_invalid_url() {
         .await
         .unwrap();
 
-    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert!(response.headers()["content-type"].to_str().unwrap().starts_with("text/html"));
 }
 
 #[tokio::test]
@@ -179,6 +269,57 @@ async fn test_csp_header_present() {
     assert!(csp_str.contains("frame-ancestors 'none'"), "CSP should prevent clickjacking");
 }
 
+/// Served when the build includes srcuri-wasm; a 404 otherwise
+#[tokio::test]
+async fn test_srcuri_wasm_assets_served() {
+    let available = sorcery_server::routes::srcuri_wasm::AVAILABLE;
+    for (uri, content_type) in [
+        ("/static/srcuri_wasm.js", "application/javascript"),
+        ("/static/srcuri_wasm_bg.wasm", "application/wasm"),
+    ] {
+        let response = create_test_app()
+            .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        if !available {
+            assert_eq!(response.status(), StatusCode::NOT_FOUND, "{}", uri);
+            continue;
+        }
+        assert_eq!(response.status(), StatusCode::OK, "{}", uri);
+        assert_eq!(response.headers().get("content-type").unwrap(), content_type);
+        assert!(response.headers().get("cache-control").unwrap().to_str().unwrap().contains("immutable"));
+    }
+}
+
+#[tokio::test]
+async fn test_pages_load_wasm_parser_allowed_by_csp() {
+    use http_body_util::BodyExt;
+
+    let assets = sorcery_server::routes::srcuri_wasm::SrcuriWasmAssets::default();
+    for uri in ["/github.com/owner/repo/blob/main/src/lib.rs", "/"] {
+        let response = create_test_app()
+            .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let csp = response.headers().get("content-security-policy").unwrap().to_str().unwrap().to_string();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let html = String::from_utf8(body.to_vec()).unwrap();
+        assert!(!html.contains("function parseGitHub"), "{} still has a JS parser", uri);
+
+        // Without the parser the pages go through the server instead
+        if !assets.available {
+            assert!(!html.contains("id=\"srcuri-wasm\""), "{} loads a parser this build lacks", uri);
+            assert!(html.contains("/?remote=") || html.contains("/api/v1/translate"), "{} has no server fallback", uri);
+            continue;
+        }
+        let script_tag = format!(r#"<script src="{}" integrity="{}""#, assets.glue_url, assets.glue_integrity);
+        assert!(html.contains(&script_tag), "{} doesn't load the parser: {}", uri, html);
+        assert!(html.contains(&format!(r#"data-module="{}""#, assets.module_url)));
+        assert!(csp.contains(&format!("'{}'", assets.glue_integrity)), "{}", csp);
+        assert!(csp.contains("'wasm-unsafe-eval'"), "{}", csp);
+    }
+}
+
 #[tokio::test]
 async fn test_javascript_url_not_in_href() {
     // Security: Verify javascript: URLs are not rendered in href attributes
@@ -283,12 +424,70 @@ async fn test_invalid_branch_shell_metachar_rejected() {
         .await
         .unwrap();
 
-    assert_eq!(response.status(), StatusCode::OK); // Error page returns 200
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
     let body = response.into_body().collect().await.unwrap().to_bytes();
     let html = String::from_utf8(body.to_vec()).unwrap();
     assert!(html.contains("Invalid branch name"));
 }
 
+#[tokio::test]
+async fn test_errors_negotiate_problem_json() {
+    let app = create_test_app().layer(axum::middleware::from_fn(sorcery_server::request_id::request_id_middleware));
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/myrepo/file.rs:10?branch=main;rm%20-rf")
+                .header("accept", "application/problem+json")
+                .header("x-request-id", "req-422")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(response.headers()["content-type"], "application/problem+json");
+    assert_eq!(response.headers()["vary"], "accept");
+    let json = response_json(response).await;
+    assert_eq!(json["status"], 422);
+    assert_eq!(json["code"], "invalid_branch");
+    assert_eq!(json["title"], "Unprocessable Entity");
+    assert!(json["detail"].as_str().unwrap().starts_with("Invalid branch name"));
+    assert_eq!(json["request_id"], "req-422");
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/?remote=not-a-valid-url")
+                .header("accept", "application/json")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(response_json(response).await["code"], "unparseable_url");
+}
+
+#[tokio::test]
+async fn test_browser_accept_gets_html_error_page() {
+    let app = create_test_app();
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/myrepo/file.rs:10?remote=github.com/x;y")
+                .header("accept", "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(response.headers()["content-type"].to_str().unwrap().starts_with("text/html"));
+}
+
 #[tokio::test]
 async fn test_invalid_branch_path_traversal_rejected() {
     use http_body_util::BodyExt;
@@ -395,15 +594,38 @@ async fn test_valid_branch_accepted() {
 }
 
 fn create_test_app() -> axum::Router {
-    use std::path::PathBuf;
+    create_test_app_with_tenants(std::path::PathBuf::from("tenants"))
+}
+
+fn create_test_app_with_tenants(tenants_dir: std::path::PathBuf) -> axum::Router {
+    create_test_app_with_options(tenants_dir, Default::default())
+}
+
+fn create_test_app_with_options(
+    tenants_dir: std::path::PathBuf,
+    options: sorcery_server::tenant::TenantOptions,
+) -> axum::Router {
     use std::sync::Arc;
-    use axum::routing::get;
 
-    let tenants_dir = PathBuf::from("tenants");
-    let tenant_manager = Arc::new(sorcery_server::tenant::TenantManager::new(tenants_dir));
-    let base_domain = "srcuri.com".to_string();
+    let tenant_manager = Arc::new(sorcery_server::tenant::TenantManager::with_options(tenants_dir, options));
+    let base_domains = vec!["srcuri.com".to_string()];
+
+    let shutdown = sorcery_server::shutdown::Shutdown::new();
+    let state = sorcery_server::AppState { tenant_manager, base_domains, shutdown };
+    test_router(state)
+}
+
+fn test_router(state: sorcery_server::AppState) -> axum::Router {
+    with_resolved_tenant(test_routes(state.clone()), state)
+}
+
+/// Resolve each request's tenant before `router` sees it, as the server does
+fn with_resolved_tenant(router: axum::Router, state: sorcery_server::AppState) -> axum::Router {
+    router.layer(axum::middleware::from_fn_with_state(state, sorcery_server::tenant::resolve_tenant_middleware))
+}
 
-    let state = sorcery_server::AppState { tenant_manager, base_domain };
+fn test_routes(state: sorcery_server::AppState) -> axum::Router {
+    use axum::routing::get;
 
     axum::Router::new()
         .route("/", get(sorcery_server::routes::root_handler))
@@ -412,8 +634,22 @@ fn create_test_app() -> axum::Router {
             "/.well-known/srcuri.json",
             get(sorcery_server::routes::wellknown_handler),
         )
-        .route("/health", get(|| async { "OK" }))
+        .route(
+            "/api/v1/translate",
+            get(sorcery_server::routes::translate_handler)
+                .post(sorcery_server::routes::translate_batch_handler),
+        )
+        .route("/health", get(sorcery_server::routes::live_handler))
+        .route("/health/live", get(sorcery_server::routes::live_handler))
+        .route("/health/ready", get(sorcery_server::routes::ready_handler))
+        .route("/version", get(sorcery_server::routes::version_handler))
+        .route("/static/srcuri_wasm.js", get(sorcery_server::routes::srcuri_wasm::glue_handler))
+        .route("/static/srcuri_wasm_bg.wasm", get(sorcery_server::routes::srcuri_wasm::module_handler))
         .fallback(get(sorcery_server::routes::catchall_handler))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            sorcery_server::routes::tenant_guard_middleware,
+        ))
         .with_state(state)
         .layer(axum::middleware::from_fn(sorcery_server::csp::csp_middleware))
 }
@@ -697,3 +933,1406 @@ async fn test_file_path_pipe_rejected() {
     let html = String::from_utf8(body.to_vec()).unwrap();
     assert!(html.contains("Invalid file path"));
 }
+
+// Translation API tests
+
+async fn response_json(response: axum::response::Response) -> serde_json::Value {
+    use http_body_util::BodyExt;
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    serde_json::from_slice(&body).unwrap()
+}
+
+#[tokio::test]
+async fn test_translate_provider_url() {
+    let app = create_test_app();
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/v1/translate?url=https://github.com/owner/repo/blob/main/src/lib.rs%23L42")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = response_json(response).await;
+    assert_eq!(json["target"]["remote"], "github.com/owner/repo");
+    assert_eq!(json["target"]["repo_name"], "repo");
+    assert_eq!(json["target"]["ref_value"], "main");
+    assert_eq!(json["target"]["file_path"], "src/lib.rs");
+    assert_eq!(json["target"]["line"], 42);
+    assert_eq!(
+        json["srcuri_url"],
+        "srcuri://repo/src/lib.rs:42?branch=main&remote=https://github.com/owner/repo"
+    );
+    assert!(json["mirror_url"].as_str().unwrap().starts_with("https://srcuri.com/repo/src/lib.rs:42"));
+}
+
+#[tokio::test]
+async fn test_translate_mirror_url() {
+    let app = create_test_app();
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/v1/translate?url=https://srcuri.com/myrepo/src/lib.rs:7%3Fbranch%3Ddevelop")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = response_json(response).await;
+    assert_eq!(json["target"]["repo_name"], "myrepo");
+    assert_eq!(json["target"]["file_path"], "src/lib.rs");
+    assert_eq!(json["target"]["line"], 7);
+    assert_eq!(json["srcuri_url"], "srcuri://myrepo/src/lib.rs:7?branch=develop");
+}
+
+#[tokio::test]
+async fn test_translate_srcuri_url() {
+    let app = create_test_app();
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/v1/translate?url=srcuri://myrepo/README.md:3")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = response_json(response).await;
+    assert_eq!(json["target"]["repo_name"], "myrepo");
+    assert_eq!(json["srcuri_url"], "srcuri://myrepo/README.md:3");
+}
+
+#[tokio::test]
+async fn test_translate_unparseable_url_returns_error_code() {
+    let app = create_test_app();
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/v1/translate?url=not-a-valid-url")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let json = response_json(response).await;
+    assert_eq!(json["error"]["code"], "unparseable_url");
+    assert!(json["error"]["message"].is_string());
+}
+
+#[tokio::test]
+async fn test_translate_invalid_branch_returns_error_code() {
+    let app = create_test_app();
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/v1/translate?url=srcuri://myrepo/file.rs%3Fbranch%3Dmain%3Brm")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let json = response_json(response).await;
+    assert_eq!(json["error"]["code"], "invalid_branch");
+}
+
+#[tokio::test]
+async fn test_translate_missing_url() {
+    let app = create_test_app();
+
+    let response = app
+        .oneshot(Request::builder().uri("/api/v1/translate").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let json = response_json(response).await;
+    assert_eq!(json["error"]["code"], "missing_url");
+}
+
+#[tokio::test]
+async fn test_translate_batch_rejects_malformed_json() {
+    let app = create_test_app();
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/v1/translate")
+                .header("content-type", "application/json")
+                .body(Body::from("{\"urls\": ["))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(response.headers()["content-type"], "application/json");
+    let json = response_json(response).await;
+    assert_eq!(json["error"]["code"], "invalid_body");
+    assert!(json["error"]["message"].is_string());
+}
+
+#[tokio::test]
+async fn test_translate_batch_too_large() {
+    let app = create_test_app();
+
+    let urls: Vec<String> = (0..101).map(|i| format!("https://github.com/owner/repo{}", i)).collect();
+    let body = serde_json::json!({ "urls": urls });
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/v1/translate")
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    let json = response_json(response).await;
+    assert_eq!(json["error"]["code"], "batch_too_large");
+}
+
+#[tokio::test]
+async fn test_translate_batch() {
+    let app = create_test_app();
+
+    let body = serde_json::json!({
+        "urls": ["https://github.com/owner/repo", "not-a-valid-url"]
+    });
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/v1/translate")
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = response_json(response).await;
+    let results = json["results"].as_array().unwrap();
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0]["target"]["remote"], "github.com/owner/repo");
+    assert_eq!(results[1]["error"]["code"], "unparseable_url");
+}
+
+// Content negotiation tests
+
+async fn get_accepting(app: &axum::Router, uri: &str, accept: &str) -> axum::response::Response {
+    app.clone()
+        .oneshot(Request::builder().uri(uri).header("accept", accept).body(Body::empty()).unwrap())
+        .await
+        .unwrap()
+}
+
+async fn response_text(response: axum::response::Response) -> String {
+    use http_body_util::BodyExt;
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    String::from_utf8(body.to_vec()).unwrap()
+}
+
+#[tokio::test]
+async fn test_mirror_url_negotiates_json_and_uri_list() {
+    let app = create_test_app();
+
+    let response = get_accepting(&app, "/myrepo/src/lib.rs:42?branch=main", "application/json").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["vary"], "accept");
+    let json = response_json(response).await;
+    assert_eq!(json["target"]["repo_name"], "myrepo");
+    assert_eq!(json["target"]["line"], 42);
+    assert_eq!(json["srcuri_url"], "srcuri://myrepo/src/lib.rs:42?branch=main");
+
+    let response = get_accepting(&app, "/myrepo/src/lib.rs:42?branch=main", "text/uri-list").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "text/uri-list");
+    assert_eq!(response_text(response).await, "srcuri://myrepo/src/lib.rs:42?branch=main\r\n");
+
+    let response = get_accepting(&app, "/myrepo/src/lib.rs:42?branch=main", "text/html").await;
+    assert_eq!(response.headers()["vary"], "accept");
+    assert!(response_text(response).await.contains("srcuri://myrepo/src/lib.rs:42"));
+}
+
+#[tokio::test]
+async fn test_provider_page_unfurls_with_parsed_link() {
+    let app = create_test_app();
+
+    let response = get_accepting(&app, "/github.com/owner/repo/blob/main/src/lib.rs", "text/html").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let html = response_text(response).await;
+    assert!(html.contains(r#"<meta property="og:title" content="src/lib.rs - repo">"#));
+    assert!(html.contains(r#"<meta name="twitter:data2" content="GitHub">"#));
+    // The line still comes from the fragment, client-side
+    assert!(html.contains("window.location.hash"));
+}
+
+#[tokio::test]
+async fn test_provider_path_negotiates_json_and_uri_list() {
+    let app = create_test_app();
+    let uri = "/github.com/owner/repo/blob/main/src/lib.rs%23L42";
+    let srcuri = "srcuri://repo/src/lib.rs:42?branch=main&remote=https://github.com/owner/repo";
+
+    let response = get_accepting(&app, uri, "application/json").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = response_json(response).await;
+    assert_eq!(json["target"]["remote"], "github.com/owner/repo");
+    assert_eq!(json["target"]["line"], 42);
+    assert_eq!(json["srcuri_url"], srcuri);
+
+    let response = get_accepting(&app, uri, "text/uri-list").await;
+    assert_eq!(response_text(response).await, format!("{}\r\n", srcuri));
+
+    // Unparseable provider paths are problems, not the interstitial
+    let response = get_accepting(&app, "/github.com/owner", "application/json").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(response_json(response).await["code"], "unparseable_url");
+}
+
+#[tokio::test]
+async fn test_provider_path_keeps_encoded_hash_in_file_names() {
+    let app = create_test_app();
+
+    let response = get_accepting(&app, "/github.com/owner/repo/blob/main/src/C%23/Program.cs", "application/json").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = response_json(response).await;
+    assert!(json["target"]["file_path"].as_str().unwrap().ends_with("/Program.cs"), "{}", json);
+    assert!(json["target"]["line"].is_null());
+
+    // A trailing line anchor is still the fragment
+    let response = get_accepting(&app, "/github.com/owner/repo/blob/main/src/C%23/Program.cs%23L7", "application/json").await;
+    let json = response_json(response).await;
+    assert!(json["target"]["file_path"].as_str().unwrap().ends_with("/Program.cs"), "{}", json);
+    assert_eq!(json["target"]["line"], 7);
+}
+
+#[tokio::test]
+async fn test_json_mirror_url_stays_on_the_request_host() {
+    let dir = write_tenants(
+        "mirror-host",
+        &[("acme", serde_json::json!({ "name": "Acme Corp", "default_remote": "github.com/acme/app", "allowed_remotes": null }))],
+    );
+    let app = create_test_app_with_tenants(dir);
+
+    for (host, uri, expected) in [
+        ("acme.srcuri.com", "/app/src/lib.rs:42", "https://acme.srcuri.com/"),
+        ("acme.srcuri.com", "/github.com/owner/repo/blob/main/src/lib.rs", "https://acme.srcuri.com/"),
+        ("attacker.example", "/app/src/lib.rs:42", "https://srcuri.com/"),
+    ] {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri(uri)
+                    .header("host", host)
+                    .header("accept", "application/json")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let json = response_json(response).await;
+        assert!(json["mirror_url"].as_str().unwrap().starts_with(expected), "{} {}: {}", host, uri, json);
+    }
+}
+
+// Tenant allowlist tests
+
+/// Write tenant configs into a fresh temporary directory
+fn write_tenants(test_name: &str, tenants: &[(&str, serde_json::Value)]) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("sorcery-tenants-{}-{}", test_name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    for (name, config) in tenants {
+        std::fs::write(dir.join(format!("{}.json", name)), config.to_string()).unwrap();
+    }
+    dir
+}
+
+fn acme_allowlist_app(test_name: &str) -> axum::Router {
+    let dir = write_tenants(
+        test_name,
+        &[(
+            "acme",
+            serde_json::json!({
+                "name": "Acme Corp",
+                "default_remote": null,
+                "allowed_remotes": ["github.com/acme/*", "gitlab.acme.com"]
+            }),
+        )],
+    );
+    create_test_app_with_tenants(dir)
+}
+
+#[tokio::test]
+async fn test_tenant_allowlist_permits_matching_mirror_remote() {
+    let app = acme_allowlist_app("mirror-ok");
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/billing/src/lib.rs:42?remote=github.com/acme/billing")
+                .header("host", "acme.srcuri.com")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_tenant_allowlist_rejects_mirror_remote() {
+    use http_body_util::BodyExt;
+
+    let app = acme_allowlist_app("mirror-reject");
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/billing/src/lib.rs:42?remote=github.com/evil/billing")
+                .header("host", "acme.srcuri.com")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let html = String::from_utf8(body.to_vec()).unwrap();
+    assert!(html.contains("Acme Corp"));
+    assert!(html.contains("Repository Not Allowed"));
+}
+
+#[tokio::test]
+async fn test_tenant_allowlist_rejects_query_passthrough() {
+    let app = acme_allowlist_app("passthrough-reject");
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/?remote=https://github.com/other/repo/blob/main/file.rs")
+                .header("host", "acme.srcuri.com")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_tenant_allowlist_permits_query_passthrough() {
+    let app = acme_allowlist_app("passthrough-ok");
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/?remote=https://github.com/acme/repo/blob/main/file.rs")
+                .header("host", "acme.srcuri.com")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+}
+
+#[tokio::test]
+async fn test_tenant_allowlist_rejects_provider_path() {
+    let app = acme_allowlist_app("provider-reject");
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/github.com/other/repo/blob/main/file.rs")
+                .header("host", "acme.srcuri.com")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_tenant_allowlist_unparseable_provider_path_is_bad_request() {
+    let app = acme_allowlist_app("provider-unparseable");
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/https://")
+                .header("host", "acme.srcuri.com")
+                .header("accept", "application/problem+json")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let json = response_json(response).await;
+    assert_eq!(json["code"], "unparseable_url");
+}
+
+#[tokio::test]
+async fn test_tenant_allowlist_not_applied_on_public_site() {
+    let app = acme_allowlist_app("public-site");
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/github.com/other/repo/blob/main/file.rs")
+                .header("host", "srcuri.com")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+// Tenant workspace defaults tests
+
+#[tokio::test]
+async fn test_tenant_workspace_registry_fills_remote_and_branch() {
+    use http_body_util::BodyExt;
+
+    let dir = write_tenants(
+        "workspace-registry",
+        &[(
+            "acme",
+            serde_json::json!({
+                "name": "Acme Corp",
+                "default_remote": "https://github.com/acme",
+                "allowed_remotes": null,
+                "workspaces": {
+                    "billing": { "remote": "https://github.com/acme/billing-service", "default_branch": "develop" }
+                }
+            }),
+        )],
+    );
+    let app = create_test_app_with_tenants(dir);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/billing/src/lib.rs:42")
+                .header("host", "acme.srcuri.com")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let html = String::from_utf8(body.to_vec()).unwrap();
+    assert!(html.contains("branch=develop"));
+    assert!(html.contains("remote=https://github.com/acme/billing-service"));
+    assert!(html.contains("src/lib.rs:42 on develop branch"));
+}
+
+#[tokio::test]
+async fn test_tenant_default_remote_without_branch() {
+    use http_body_util::BodyExt;
+
+    let dir = write_tenants(
+        "default-remote",
+        &[(
+            "acme",
+            serde_json::json!({
+                "name": "Acme Corp",
+                "default_remote": "https://github.com/acme",
+                "allowed_remotes": null
+            }),
+        )],
+    );
+    let app = create_test_app_with_tenants(dir);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/payments/src/lib.rs:7")
+                .header("host", "acme.srcuri.com")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let html = String::from_utf8(body.to_vec()).unwrap();
+    assert!(html.contains("remote=https://github.com/acme/payments"));
+    assert!(!html.contains("main branch"), "OG text should not assume a branch");
+}
+
+// Strict tenant mode tests
+
+fn strict_app(test_name: &str) -> axum::Router {
+    let dir = write_tenants(
+        test_name,
+        &[("acme", serde_json::json!({ "name": "Acme Corp", "default_remote": null, "allowed_remotes": null }))],
+    );
+    let options = sorcery_server::tenant::TenantOptions { strict: true, ..Default::default() };
+    create_test_app_with_options(dir, options)
+}
+
+#[tokio::test]
+async fn test_strict_mode_unknown_tenant_is_404() {
+    use http_body_util::BodyExt;
+
+    let app = strict_app("strict-unknown");
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/.well-known/srcuri.json")
+                .header("host", "xyz123.srcuri.com")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let html = String::from_utf8(body.to_vec()).unwrap();
+    assert!(html.contains("No Such Organization"));
+}
+
+#[tokio::test]
+async fn test_reserved_subdomain_is_404_even_when_not_strict() {
+    let app = create_test_app();
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/.well-known/srcuri.json")
+                .header("host", "admin.srcuri.com")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_only_probe_routes_skip_the_tenant_guard() {
+    let app = strict_app("strict-probes");
+
+    for (host, uri, expected) in [
+        ("api.srcuri.com", "/health/ready", StatusCode::OK),
+        ("xyz123.srcuri.com", "/health/live", StatusCode::OK),
+        ("api.srcuri.com", "/health/src/lib.rs", StatusCode::NOT_FOUND),
+        ("xyz123.srcuri.com", "/health/x.rs", StatusCode::NOT_FOUND),
+    ] {
+        let response = app
+            .clone()
+            .oneshot(Request::builder().uri(uri).header("host", host).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), expected, "{}{}", host, uri);
+    }
+}
+
+#[tokio::test]
+async fn test_strict_mode_known_tenant_is_served() {
+    let app = strict_app("strict-known");
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/myrepo/file.rs:1")
+                .header("host", "acme.srcuri.com")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_strict_mode_public_site_is_served() {
+    let app = strict_app("strict-public");
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/myrepo/file.rs:1")
+                .header("host", "srcuri.com")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_wellknown_resolves_tenant_from_host() {
+    let dir = write_tenants(
+        "wellknown-tenant",
+        &[("acme", serde_json::json!({ "name": "Acme Corp", "default_remote": null, "allowed_remotes": null }))],
+    );
+    let app = create_test_app_with_tenants(dir);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/.well-known/srcuri.json")
+                .header("host", "acme.srcuri.com:443")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = response_json(response).await;
+    assert_eq!(json["name"], "Acme Corp");
+}
+
+#[tokio::test]
+async fn test_wellknown_hides_private_tenant_fields() {
+    let dir = write_tenants(
+        "wellknown-private",
+        &[(
+            "acme",
+            serde_json::json!({
+                "name": "Acme Corp",
+                "default_remote": "https://github.com/acme",
+                "allowed_remotes": ["github.com/acme/*"],
+                "workspaces": { "billing": { "remote": "https://gitlab.acme.com/finance/billing" } },
+                "custom_domains": ["code.acme.com"],
+                "rate_limit": { "per_second": 1, "burst_size": 300 },
+                "log_full_paths": true
+            }),
+        )],
+    );
+    let response = create_test_app_with_tenants(dir)
+        .oneshot(
+            Request::builder()
+                .uri("/.well-known/srcuri.json")
+                .header("host", "acme.srcuri.com")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    let json = response_json(response).await;
+    assert_eq!(
+        json,
+        serde_json::json!({
+            "name": "Acme Corp",
+            "default_remote": "https://github.com/acme",
+            "allowed_remotes": ["github.com/acme/*"]
+        })
+    );
+    let body = json.to_string();
+    for private in ["workspaces", "gitlab.acme.com", "code.acme.com", "rate_limit", "log_full_paths"] {
+        assert!(!body.contains(private), "{} leaked: {}", private, body);
+    }
+}
+
+fn rate_limited_app(tenants_dir: std::path::PathBuf, config: sorcery_server::config::RateLimitConfig) -> axum::Router {
+    use std::sync::Arc;
+
+    let tenant_manager = Arc::new(sorcery_server::tenant::TenantManager::new(tenants_dir));
+    let shutdown = sorcery_server::shutdown::Shutdown::new();
+    let state = sorcery_server::AppState { tenant_manager, base_domains: vec!["srcuri.com".to_string()], shutdown };
+    let limiter = Arc::new(sorcery_server::ratelimit::RateLimiter::new(config));
+    let proxies = sorcery_server::client_ip::TrustedProxies::parse(&["10.0.0.0/8".to_string()]).unwrap();
+    let app = test_routes(state.clone())
+        .layer(axum::middleware::from_fn_with_state(
+            limiter,
+            sorcery_server::ratelimit::rate_limit_middleware,
+        ));
+    with_resolved_tenant(app, state)
+        .layer(axum::middleware::from_fn_with_state(
+            Arc::new(proxies),
+            sorcery_server::client_ip::client_ip_middleware,
+        ))
+}
+
+fn tight_limits(burst_size: u32) -> sorcery_server::config::RateLimitConfig {
+    let policy = sorcery_server::config::RateLimitPolicy { enabled: true, per_second: 60, burst_size };
+    sorcery_server::config::RateLimitConfig {
+        pages: policy.clone(),
+        api: policy.clone(),
+        static_assets: policy,
+        ..Default::default()
+    }
+}
+
+/// Request from `client` via a trusted proxy at 10.0.0.2
+async fn get_via_proxy(app: &axum::Router, uri: &str, host: &str, client: &str) -> axum::response::Response {
+    let peer: std::net::SocketAddr = "10.0.0.2:41000".parse().unwrap();
+    app.clone()
+        .oneshot(
+            Request::builder()
+                .uri(uri)
+                .header("host", host)
+                .header("x-forwarded-for", client)
+                .extension(axum::extract::ConnectInfo(peer))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+}
+
+async fn get_from(app: &axum::Router, uri: &str, host: &str) -> axum::response::Response {
+    get_via_proxy(app, uri, host, "203.0.113.7").await
+}
+
+#[tokio::test]
+async fn test_health_is_not_rate_limited() {
+    let app = rate_limited_app(std::path::PathBuf::from("tenants"), tight_limits(1));
+
+    for _ in 0..5 {
+        let response = get_from(&app, "/health", "srcuri.com").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers().get("ratelimit-limit").is_none());
+    }
+}
+
+#[tokio::test]
+async fn test_page_rate_limit_renders_429_page() {
+    use http_body_util::BodyExt;
+
+    let app = rate_limited_app(std::path::PathBuf::from("tenants"), tight_limits(2));
+
+    let first = get_from(&app, "/myrepo/file.rs:1", "srcuri.com").await;
+    assert_eq!(first.status(), StatusCode::OK);
+    assert_eq!(first.headers().get("ratelimit-limit").unwrap(), "2");
+    assert_eq!(first.headers().get("ratelimit-remaining").unwrap(), "1");
+    get_from(&app, "/myrepo/file.rs:1", "srcuri.com").await;
+
+    let limited = get_from(&app, "/myrepo/file.rs:1", "srcuri.com").await;
+    assert_eq!(limited.status(), StatusCode::TOO_MANY_REQUESTS);
+    let retry_after: u64 = limited.headers().get("retry-after").unwrap().to_str().unwrap().parse().unwrap();
+    assert!(retry_after > 0 && retry_after <= 60);
+    assert_eq!(limited.headers().get("ratelimit-remaining").unwrap(), "0");
+    assert_eq!(limited.headers()["vary"], "accept");
+    let body = limited.into_body().collect().await.unwrap().to_bytes();
+    let html = String::from_utf8(body.to_vec()).unwrap();
+    assert!(html.contains("Too Many Requests"));
+}
+
+#[tokio::test]
+async fn test_page_rate_limit_negotiates_problem_json() {
+    let app = rate_limited_app(std::path::PathBuf::from("tenants"), tight_limits(1));
+    assert_eq!(get_from(&app, "/myrepo/file.rs:1", "srcuri.com").await.status(), StatusCode::OK);
+
+    let peer: std::net::SocketAddr = "10.0.0.2:41000".parse().unwrap();
+    for accept in ["application/problem+json", "application/json"] {
+        let limited = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/myrepo/file.rs:1")
+                    .header("host", "srcuri.com")
+                    .header("x-forwarded-for", "203.0.113.7")
+                    .header("accept", accept)
+                    .extension(axum::extract::ConnectInfo(peer))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(limited.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(limited.headers()["content-type"], "application/problem+json");
+        assert_eq!(limited.headers()["vary"], "accept");
+        assert!(limited.headers().get("retry-after").is_some());
+        let json = response_json(limited).await;
+        assert_eq!(json["code"], "rate_limited", "{}", json);
+        assert_eq!(json["status"], 429);
+    }
+}
+
+#[tokio::test]
+async fn test_api_rate_limit_returns_json() {
+    let app = rate_limited_app(std::path::PathBuf::from("tenants"), tight_limits(1));
+    let uri = "/api/v1/translate?url=https://github.com/acme/repo/blob/main/a.rs";
+
+    assert_eq!(get_from(&app, uri, "srcuri.com").await.status(), StatusCode::OK);
+    let limited = get_from(&app, uri, "srcuri.com").await;
+    assert_eq!(limited.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert!(limited.headers().get("retry-after").is_some());
+    let json = response_json(limited).await;
+    assert_eq!(json["error"]["code"], "rate_limited");
+}
+
+#[tokio::test]
+async fn test_tenant_rate_limit_override() {
+    let dir = write_tenants(
+        "ratelimit-tenant",
+        &[(
+            "acme",
+            serde_json::json!({
+                "name": "Acme Corp",
+                "default_remote": null,
+                "allowed_remotes": null,
+                "rate_limit": { "per_second": 60, "burst_size": 1 }
+            }),
+        )],
+    );
+    let app = rate_limited_app(dir, tight_limits(10));
+
+    assert_eq!(get_from(&app, "/myrepo/file.rs:1", "acme.srcuri.com").await.status(), StatusCode::OK);
+    let limited = get_from(&app, "/myrepo/file.rs:1", "acme.srcuri.com").await;
+    assert_eq!(limited.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    // The same client still has its budget on the public site
+    let public = get_from(&app, "/myrepo/file.rs:1", "srcuri.com").await;
+    assert_eq!(public.status(), StatusCode::OK);
+    assert_eq!(public.headers().get("ratelimit-limit").unwrap(), "10");
+}
+
+#[tokio::test]
+async fn test_rate_limit_uses_client_behind_trusted_proxy() {
+    let app = rate_limited_app(std::path::PathBuf::from("tenants"), tight_limits(1));
+
+    let first = get_via_proxy(&app, "/myrepo/file.rs:1", "srcuri.com", "203.0.113.7").await;
+    assert_eq!(first.status(), StatusCode::OK);
+    // A different client behind the same proxy has its own budget
+    let other = get_via_proxy(&app, "/myrepo/file.rs:1", "srcuri.com", "203.0.113.8").await;
+    assert_eq!(other.status(), StatusCode::OK);
+    // Prepending a spoofed hop doesn't help: the chain is read from the right
+    let spoofed = get_via_proxy(&app, "/myrepo/file.rs:1", "srcuri.com", "198.51.100.1, 203.0.113.7").await;
+    assert_eq!(spoofed.status(), StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[tokio::test]
+async fn test_forwarded_headers_ignored_from_untrusted_peer() {
+    let app = rate_limited_app(std::path::PathBuf::from("tenants"), tight_limits(1));
+    let peer: std::net::SocketAddr = "203.0.113.50:41000".parse().unwrap();
+
+    let mut statuses = Vec::new();
+    for spoofed in ["198.51.100.1", "198.51.100.2"] {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/myrepo/file.rs:1")
+                    .header("x-forwarded-for", spoofed)
+                    .extension(axum::extract::ConnectInfo(peer))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        statuses.push(response.status());
+    }
+    assert_eq!(statuses, vec![StatusCode::OK, StatusCode::TOO_MANY_REQUESTS]);
+}
+
+#[tokio::test]
+async fn test_metrics_endpoint_counts_requests_and_rejections() {
+    use axum::routing::get;
+    use http_body_util::BodyExt;
+
+    let app = create_test_app()
+        .route("/metrics", get(sorcery_server::metrics::metrics_handler))
+        .layer(axum::middleware::from_fn(sorcery_server::metrics::metrics_middleware));
+
+    for uri in ["/myrepo/file.rs:1?branch=..bad", "/myrepo/file.rs:1", "/.well-known/srcuri.json"] {
+        app.clone()
+            .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+    }
+
+    let response = app
+        .oneshot(Request::builder().uri("/metrics").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers()["content-type"].to_str().unwrap().starts_with("text/plain"));
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let text = String::from_utf8(body.to_vec()).unwrap();
+
+    assert!(text.contains("sorcery_http_requests_total{route=\"mirror\",status=\"200\"}"), "{}", text);
+    assert!(text.contains("sorcery_http_requests_total{route=\"wellknown\",status=\"200\"}"));
+    assert!(text.contains("sorcery_http_request_duration_seconds_bucket{route=\"mirror\""));
+    assert!(text.contains("sorcery_validation_rejections_total{param=\"branch\"}"));
+    assert!(text.contains("sorcery_tenant_cache_lookups_total"));
+}
+
+fn request_id_app() -> axum::Router {
+    create_test_app().layer(axum::middleware::from_fn(sorcery_server::request_id::request_id_middleware))
+}
+
+#[tokio::test]
+async fn test_request_id_is_generated_and_echoed() {
+    let response = request_id_app()
+        .oneshot(Request::builder().uri("/health").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+
+    let id = response.headers()["x-request-id"].to_str().unwrap();
+    assert_eq!(id.len(), 36, "expected a UUID, got {}", id);
+}
+
+#[tokio::test]
+async fn test_request_id_is_propagated() {
+    let response = request_id_app()
+        .oneshot(
+            Request::builder()
+                .uri("/health")
+                .header("x-request-id", "lb-7f3c2a9e")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.headers()["x-request-id"], "lb-7f3c2a9e");
+}
+
+#[tokio::test]
+async fn test_unsafe_request_id_is_replaced() {
+    let response = request_id_app()
+        .oneshot(
+            Request::builder()
+                .uri("/health")
+                .header("x-request-id", "<script>alert(1)</script>")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    let id = response.headers()["x-request-id"].to_str().unwrap();
+    assert!(!id.contains("script"));
+    assert_eq!(id.len(), 36);
+}
+
+#[tokio::test]
+async fn test_error_page_shows_request_id() {
+    use http_body_util::BodyExt;
+
+    let response = request_id_app()
+        .oneshot(
+            Request::builder()
+                .uri("/?remote=not-a-valid-url")
+                .header("x-request-id", "bug-report-42")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let html = String::from_utf8(body.to_vec()).unwrap();
+    assert!(html.contains("Request ID: <code>bug-report-42</code>"), "{}", html);
+}
+
+#[tokio::test]
+async fn test_spans_are_exported_to_otlp_collector() {
+    use axum::{body::Bytes, routing::post};
+    use opentelemetry_proto::tonic::collector::trace::v1::ExportTraceServiceRequest;
+    use prost::Message;
+    use std::sync::{Arc, Mutex};
+    use tracing::instrument::WithSubscriber;
+    use tracing_subscriber::{layer::SubscriberExt, Layer};
+
+    // In-process stand-in for an OTLP/HTTP collector
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let sink = received.clone();
+    let collector = axum::Router::new().route(
+        "/v1/traces",
+        post(move |body: Bytes| async move {
+            sink.lock().unwrap().push(ExportTraceServiceRequest::decode(body).unwrap());
+            StatusCode::OK
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, collector).await });
+
+    let provider = sorcery_server::telemetry::tracer_provider(&format!("http://{}", addr), "sorcery-test").unwrap();
+    // Logs filtered as the Dockerfile's RUST_LOG does; the debug spans must still be exported
+    let log_layer = tracing_subscriber::fmt::layer()
+        .with_writer(std::io::sink)
+        .with_filter(tracing_subscriber::EnvFilter::new("sorcery_server=info"));
+    let subscriber = tracing_subscriber::registry()
+        .with(log_layer)
+        .with(sorcery_server::telemetry::layer(&provider));
+    // The production layer stack, so tenant resolution runs where it does in the server
+    let tenant_manager = Arc::new(sorcery_server::tenant::TenantManager::new("tenants".into()));
+    let shutdown = sorcery_server::shutdown::Shutdown::new();
+    let state = sorcery_server::AppState { tenant_manager, base_domains: vec!["srcuri.com".to_string()], shutdown };
+    let proxies = Arc::new(sorcery_server::client_ip::TrustedProxies::parse(&[]).unwrap());
+    let app = sorcery_server::with_request_layers(test_routes(state.clone()), state, proxies);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/?remote=not-a-valid-url")
+                .header("host", "nosuchtenant.srcuri.com")
+                .header("traceparent", "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .with_subscriber(subscriber)
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    // The request span stays open until the response body is dropped
+    drop(response);
+
+    tokio::task::spawn_blocking(move || provider.force_flush()).await.unwrap().unwrap();
+
+    let requests = received.lock().unwrap();
+    let spans: Vec<_> = requests
+        .iter()
+        .flat_map(|r| &r.resource_spans)
+        .flat_map(|r| &r.scope_spans)
+        .flat_map(|s| &s.spans)
+        .collect();
+    let names: Vec<&str> = spans.iter().map(|s| s.name.as_str()).collect();
+    for expected in ["detect_subdomain", "tenant_lookup", "parse_url", "render_template"] {
+        assert!(names.contains(&expected), "missing {} in {:?}", expected, names);
+    }
+
+    // Every span joins the caller's trace, and the request span's parent is the caller's span
+    let trace_id = [0x4b, 0xf9, 0x2f, 0x35, 0x77, 0xb3, 0x4d, 0xa6, 0xa3, 0xce, 0x92, 0x9d, 0x0e, 0x0e, 0x47, 0x36];
+    assert!(spans.iter().all(|s| s.trace_id == trace_id));
+    let root = spans.iter().find(|s| s.name.starts_with("GET ")).expect("request span");
+    assert_eq!(root.name, "GET passthrough");
+    assert_eq!(root.parent_span_id, [0x00, 0xf0, 0x67, 0xaa, 0x0b, 0xa9, 0x02, 0xb7]);
+    // Tenant resolution happens inside the request span, not in orphan traces
+    for name in ["detect_subdomain", "tenant_lookup"] {
+        let span = spans.iter().find(|s| s.name == name).unwrap();
+        assert_eq!(span.parent_span_id, root.span_id, "{} is not a child of the request span", name);
+    }
+    assert!(!format!("{:?}", spans).contains("not-a-valid-url"));
+}
+
+/// Self-signed certificate for `hosts`, written as `<dir>/<name>.crt` and `.key`; returns its DER
+fn write_self_signed(dir: &std::path::Path, name: &str, hosts: &[&str]) -> Vec<u8> {
+    let generated = rcgen::generate_simple_self_signed(hosts.iter().map(|h| h.to_string()).collect::<Vec<_>>()).unwrap();
+    std::fs::write(dir.join(format!("{}.crt", name)), generated.cert.pem()).unwrap();
+    std::fs::write(dir.join(format!("{}.key", name)), generated.key_pair.serialize_pem()).unwrap();
+    generated.cert.der().to_vec()
+}
+
+/// Handshake with `server_name` as SNI, trusting only `trusted`, GET /health,
+/// and return the served certificate and response
+async fn tls_get_health(addr: std::net::SocketAddr, server_name: &str, trusted: &[u8]) -> (Vec<u8>, String) {
+    use std::sync::Arc;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let mut roots = rustls::RootCertStore::empty();
+    roots.add(rustls_pki_types::CertificateDer::from(trusted.to_vec())).unwrap();
+    let client = rustls::ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    let connector = tokio_rustls::TlsConnector::from(Arc::new(client));
+
+    let tcp = tokio::net::TcpStream::connect(addr).await.unwrap();
+    let name = rustls_pki_types::ServerName::try_from(server_name.to_string()).unwrap();
+    let mut stream = connector.connect(name, tcp).await.unwrap();
+    let served = stream.get_ref().1.peer_certificates().unwrap()[0].to_vec();
+
+    let request = format!("GET /health HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n", server_name);
+    stream.write_all(request.as_bytes()).await.unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    (served, response)
+}
+
+#[tokio::test]
+async fn test_tls_sni_and_certificate_reload() {
+    use std::sync::Arc;
+
+    let dir = std::env::temp_dir().join(format!("sorcery-tls-{}", std::process::id()));
+    let domains = dir.join("domains");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&domains).unwrap();
+
+    let default_der = write_self_signed(&dir, "default", &["srcuri.com", "*.srcuri.com"]);
+    let tenant_der = write_self_signed(&domains, "links.acme.com", &["links.acme.com"]);
+
+    let tls_config = sorcery_server::config::TlsConfig {
+        cert: Some(dir.join("default.crt")),
+        key: Some(dir.join("default.key")),
+        certs_dir: Some(domains.clone()),
+        ..Default::default()
+    };
+    let store = Arc::new(sorcery_server::tls::CertStore::load(&tls_config).unwrap());
+    assert_eq!(store.custom_hosts(), vec!["links.acme.com"]);
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.set_nonblocking(true).unwrap();
+    let addr = listener.local_addr().unwrap();
+    let rustls_config = axum_server::tls_rustls::RustlsConfig::from_config(store.clone().server_config());
+    let app = create_test_app();
+    tokio::spawn(async move {
+        axum_server::from_tcp_rustls(listener, rustls_config)
+            .serve(app.into_make_service_with_connect_info::<std::net::SocketAddr>())
+            .await
+    });
+
+    // The tenant's custom domain gets its own certificate; everything else gets the default
+    let (served, response) = tls_get_health(addr, "links.acme.com", &tenant_der).await;
+    assert_eq!(served, tenant_der);
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    let (served, _) = tls_get_health(addr, "acme.srcuri.com", &default_der).await;
+    assert_eq!(served, default_der);
+
+    // A rotated certificate is served after reload, without restarting the listener
+    let rotated_der = write_self_signed(&domains, "links.acme.com", &["links.acme.com"]);
+    store.reload();
+    let (served, _) = tls_get_health(addr, "links.acme.com", &rotated_der).await;
+    assert_eq!(served, rotated_der);
+
+    // A broken rotation keeps the last good certificate
+    std::fs::write(domains.join("links.acme.com.key"), "not a key").unwrap();
+    store.reload();
+    let (served, _) = tls_get_health(addr, "links.acme.com", &rotated_der).await;
+    assert_eq!(served, rotated_der);
+}
+
+/// Raw HTTP/1.1 GET over a Unix socket, returning the response head and body
+#[cfg(unix)]
+async fn unix_get(path: &std::path::Path, uri: &str, client: &str) -> String {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let mut stream = tokio::net::UnixStream::connect(path).await.unwrap();
+    let request = format!(
+        "GET {} HTTP/1.1\r\nHost: srcuri.com\r\nX-Forwarded-For: {}\r\nConnection: close\r\n\r\n",
+        uri, client
+    );
+    stream.write_all(request.as_bytes()).await.unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    response
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_unix_socket_listener() {
+    use std::os::unix::fs::PermissionsExt;
+    use std::sync::Arc;
+
+    let dir = std::env::temp_dir().join(format!("sorcery-unix-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("http.sock");
+
+    // The production layer stack with no trusted proxies: the socket peer is
+    // trusted for X-Forwarded-For without listing 127.0.0.1
+    let tenant_manager = Arc::new(sorcery_server::tenant::TenantManager::new("tenants".into()));
+    let state = sorcery_server::AppState {
+        tenant_manager,
+        base_domains: vec!["srcuri.com".to_string()],
+        shutdown: sorcery_server::shutdown::Shutdown::new(),
+    };
+    let limiter = Arc::new(sorcery_server::ratelimit::RateLimiter::new(tight_limits(1)));
+    let proxies = Arc::new(sorcery_server::client_ip::TrustedProxies::default());
+    let routes = test_routes(state.clone())
+        .layer(axum::middleware::from_fn_with_state(limiter, sorcery_server::ratelimit::rate_limit_middleware));
+    let app = sorcery_server::with_request_layers(routes, state, proxies);
+
+    let listener = sorcery_server::listener::bind_unix(&path, 0o600).unwrap();
+    assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+    let shutdown = sorcery_server::shutdown::Shutdown::new();
+    let server = tokio::spawn(sorcery_server::listener::serve_unix(listener, app, shutdown.stopped()));
+
+    // Rate limits are keyed by the forwarded client, not shared by everyone behind the socket
+    let response = unix_get(&path, "/", "203.0.113.7").await;
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    let response = unix_get(&path, "/", "203.0.113.7").await;
+    assert!(response.starts_with("HTTP/1.1 429"), "{}", response);
+    let response = unix_get(&path, "/", "203.0.113.8").await;
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+
+    // A live socket is never taken over; one left behind after shutdown is replaced
+    let err = sorcery_server::listener::bind_unix(&path, 0o600).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::AddrInUse);
+    shutdown.stop();
+    tokio::time::timeout(std::time::Duration::from_secs(5), server).await.unwrap().unwrap().unwrap();
+    sorcery_server::listener::bind_unix(&path, 0o660).unwrap();
+    assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o660);
+    // The private directory the socket is bound in doesn't outlive the bind
+    let entries: Vec<_> = std::fs::read_dir(&dir).unwrap().map(|e| e.unwrap().file_name()).collect();
+    assert_eq!(entries, vec![std::ffi::OsString::from("http.sock")]);
+
+    let regular_file = dir.join("not-a-socket");
+    std::fs::write(&regular_file, "keep me").unwrap();
+    assert!(sorcery_server::listener::bind_unix(&regular_file, 0o660).is_err());
+    assert_eq!(std::fs::read_to_string(&regular_file).unwrap(), "keep me");
+}
+
+// Shared parser fixtures
+// Path-based provider links are parsed by `parse_remote_url` on the server and
+// by srcuri-wasm, the same parser compiled to WebAssembly, in the browser,
+// which alone sees the `#L42` fragment. tests/fixtures/provider_urls.json is
+// run against both entry points, so they can't drift apart; the browser side
+// gets the input the provider page builds, not the fixture URL as written.
+
+#[derive(serde::Deserialize)]
+struct ParserFixture {
+    name: String,
+    url: String,
+    #[serde(default)]
+    fragment: Option<String>,
+    #[serde(default)]
+    error: bool,
+    #[serde(default)]
+    expected: Option<ParsedLink>,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+struct ParsedLink {
+    remote: String,
+    repo_name: String,
+    #[serde(rename = "ref")]
+    ref_value: Option<String>,
+    file_path: Option<String>,
+    line: Option<u32>,
+    srcuri_url: String,
+}
+
+impl ParserFixture {
+    fn input(&self) -> String {
+        match &self.fragment {
+            Some(fragment) => format!("{}#{}", self.url, fragment),
+            None => self.url.clone(),
+        }
+    }
+}
+
+fn parser_fixtures() -> Vec<ParserFixture> {
+    let fixtures: Vec<ParserFixture> = serde_json::from_str(include_str!("fixtures/provider_urls.json")).unwrap();
+    for fixture in &fixtures {
+        assert!(fixture.error != fixture.expected.is_some(), "{}: needs exactly one of error or expected", fixture.name);
+    }
+    fixtures
+}
+
+/// Run every fixture through `parse`, which returns the parsed link or an error
+/// message, and fail with the full list of divergences
+fn check_parser_fixtures(parser: &str, parse: impl Fn(&str) -> Result<ParsedLink, String>) {
+    let mut failures = Vec::new();
+    for fixture in parser_fixtures() {
+        match (parse(&fixture.input()), &fixture.expected) {
+            // srcuri_url is compared byte for byte: the desktop app gets
+            // whatever the page built, so the encoding must match too
+            (Ok(actual), Some(expected)) => {
+                if actual != *expected {
+                    failures.push(format!("{}:\n  expected {:?}\n  got      {:?}", fixture.name, expected, actual));
+                }
+            }
+            (Err(message), Some(_)) => failures.push(format!("{}: unexpected error: {}", fixture.name, message)),
+            (Ok(actual), None) => failures.push(format!("{}: expected an error, got {:?}", fixture.name, actual)),
+            (Err(_), None) => {}
+        }
+    }
+    assert!(failures.is_empty(), "{} disagrees with provider_urls.json:\n{}", parser, failures.join("\n"));
+}
+
+#[test]
+fn test_parser_fixtures_rust() {
+    check_parser_fixtures("parse_remote_url", |input| {
+        let target = sorcery_server::parsing::parse_remote_url(input).map_err(|e| e.message)?;
+        let translation =
+            sorcery_server::routes::api::translate(input, &["srcuri.com".to_string()]).map_err(|e| e.message())?;
+        Ok(ParsedLink {
+            remote: target.remote,
+            repo_name: target.repo_name,
+            ref_value: target.ref_value,
+            file_path: target.file_path,
+            line: target.line,
+            srcuri_url: translation.srcuri_url,
+        })
+    });
+}
+
+/// The string the provider page hands to `parseRemoteUrl`: the address bar
+/// parsed the way the browser parses it, then `location.pathname` without its
+/// leading slash, plus `location.search` and `location.hash`
+fn provider_page_input(link: &str) -> String {
+    let location = url::Url::parse("https://srcuri.com/").unwrap().join(&format!("/{}", link)).unwrap();
+    let mut input = location.path().trim_start_matches('/').to_string();
+    if let Some(query) = location.query() {
+        input.push('?');
+        input.push_str(query);
+    }
+    if let Some(fragment) = location.fragment() {
+        input.push('#');
+        input.push_str(fragment);
+    }
+    input
+}
+
+/// srcuri-wasm's entry point, fed what the provider page builds from its
+/// location. The page's script itself runs against the compiled module in
+/// tests/browser/provider_page.mjs.
+#[test]
+fn test_parser_fixtures_provider_page_input() {
+    check_parser_fixtures("srcuri_wasm::parse_link", |input| {
+        let link = srcuri_wasm::parse_link(&provider_page_input(input)).map_err(|e| e.message)?;
+        Ok(ParsedLink {
+            remote: link.remote,
+            repo_name: link.repo_name,
+            ref_value: link.ref_value,
+            file_path: link.file_path,
+            line: link.line,
+            srcuri_url: link.srcuri_url,
+        })
+    });
+}
+
+#[test]
+fn test_provider_page_input_keeps_query_and_fragment() {
+    assert_eq!(
+        provider_page_input("https://dev.azure.com/org/_git/repo?path=/a.rs&version=GBmain#top"),
+        "https://dev.azure.com/org/_git/repo?path=/a.rs&version=GBmain#top"
+    );
+    assert_eq!(
+        provider_page_input("github.com/owner/repo/blob/main/my notes.md#L4"),
+        "github.com/owner/repo/blob/main/my%20notes.md#L4"
+    );
+}